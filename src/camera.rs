@@ -12,6 +12,7 @@ pub struct Camera {
 	pub azimuth:      f32,
 	pub elevation:    f32,
 	pub aspect_ratio: f32,
+	pub eye:          Point3<f32>,
 	pub view:         Matrix4<f32>,
 	pub projection:   Matrix4<f32>,
 }
@@ -39,6 +40,7 @@ impl Camera {
 		Camera {
 			azimuth: azimuth,
 			elevation: elevation,
+			eye: eye,
 			view: view,
 			projection: projection,
 			aspect_ratio: aspect_ratio,
@@ -1,5 +1,8 @@
 use std::collections::{HashMap};
+use std::io;
+use std::io::{Write};
 use std::mem;
+use std::process;
 use std::ptr;
 use std::sync::{Arc};
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
@@ -7,16 +10,17 @@ use std::thread;
 
 use crossbeam::sync::{MsQueue};
 use glium::{DisplayBuild};
-use glium::glutin::{WindowBuilder, CursorState, get_primary_monitor};
+use glium::glutin::{CursorState, HeadlessRendererBuilder, get_primary_monitor};
 use mioco;
 use mioco::{Mioco, Config};
 use mioco::sync::{Mutex, RwLock};
 use mioco::sync::mpsc::{Receiver, Sender, channel};
 use time;
 
-use input::{InputContext, InputFrame};
+use context::{Options};
+use input::{InputContext, InputEvent, InputFrame, INPUT_FREQUENCY};
 use physics::{PhysicsContext, PhysicsFrame};
-use render::{RenderContext, RenderFrame, RenderProcessor, RenderToken};
+use render::{Image, RenderContext, RenderFrame, RenderProcessor, RenderToken, window_builder};
 use scheduler::{BalancingScheduler};
 
 
@@ -36,19 +40,16 @@ unsafe impl Send for Context {}
 unsafe impl Sync for Context {}
 
 pub fn init() {
-	const INPUT_FREQUENCY: u64 = 120;
 	const RENDER_FREQUENCY: u64 = 60;
 
+	let options = Options::from_args();
+	if options.headless { return run_headless(options) }
+
 	let window_size = get_primary_monitor().get_dimensions();
 	let window_size = (window_size.0/2, window_size.1/2); // FIXME: macbook scaling bs
 	let aspect_ratio = (window_size.0 as f32) / (window_size.1 as f32);
 
-	let glium_context = WindowBuilder::new()
-		.with_dimensions(window_size.0, window_size.1)
-		.with_title(format!("SG"))
-		.with_depth_buffer(24)
-		.with_decorations(false)
-		.build_glium().unwrap();
+	let glium_context = window_builder(window_size, false).build_glium().unwrap();
 
 	glium_context.get_window().unwrap().set_cursor_state(CursorState::Grab).ok();
 
@@ -56,30 +57,18 @@ pub fn init() {
 
 	let (render_tokens_sender, render_tokens_receiver) = channel::<RenderToken>();
 
-	let physics_zero = Arc::new(PhysicsFrame::frame_zero(aspect_ratio));
+	let physics_zero = Arc::new(PhysicsFrame::frame_zero(aspect_ratio, options.seed));
 
-	let render_context = RenderContext::new(&glium_context, q.clone(), window_size, &physics_zero.ik_chains);
+	let render_context = RenderContext::new(&glium_context, q.clone(), &physics_zero.ik_chains, &physics_zero.bodies);
 
-	let context = Arc::new(
-		Context {
-			exit: AtomicBool::new(false),
-			input_senders: MsQueue::new(),
-			last_physics_frame: RwLock::new(physics_zero),
-			render_tokens_length: AtomicUsize::new(0),
-			physics_continuations: Arc::new(Mutex::new(HashMap::new())),
-
-			input:   InputContext::new(),
-			physics: PhysicsContext::new(),
-			render:  render_context,
-		}
-	);
+	let context = Arc::new(Context::new(&options, physics_zero, Some(render_context)));
 
 	{
 		let context = context.clone();
-		thread::spawn(move || { spawn_coroutines(context, render_tokens_receiver); });
+		thread::spawn(move || { spawn_coroutines(context, Some(render_tokens_receiver)); });
 	}
 
-	let mut render_processor = RenderProcessor::new(q, glium_context);
+	let mut render_processor = RenderProcessor::new(q, glium_context, window_size);
 	let mut last_input_time = time::precise_time_ns() / 1_000_000;
 	let mut last_render_time = last_input_time;
 
@@ -90,6 +79,16 @@ pub fn init() {
 
 		context.input.post_input_events(events);
 
+		let fullscreen = context.fullscreen.load(Ordering::Relaxed);
+		if fullscreen != render_processor.fullscreen {
+			let (width, height) = render_processor.set_fullscreen(fullscreen);
+			context.input.post_input_events(vec![InputEvent::Resized{ width: width, height: height }]);
+
+			// a refused switch is given up on rather than retried every time round
+			//
+			context.fullscreen.store(render_processor.fullscreen, Ordering::Relaxed);
+		}
+
 		let time = time::precise_time_ns() / 1_000_000;
 
 		while last_input_time < time {
@@ -117,6 +116,86 @@ pub fn init() {
 	}
 }
 
+// the camera's aspect ratio when headless, and the size of --screenshot / --golden images
+//
+const HEADLESS_SIZE: (u32, u32) = (1280, 720);
+
+// runs the input and physics coroutines without a window or GL, as fast as they will go,
+// for `--frames` frames against replayed input (or none at all)
+//
+fn run_headless(options: Options) {
+	const MAX_INPUT_FRAMES_AHEAD: u64 = 2;
+
+	let aspect_ratio = (HEADLESS_SIZE.0 as f32) / (HEADLESS_SIZE.1 as f32);
+
+	let physics_zero = Arc::new(PhysicsFrame::frame_zero(aspect_ratio, options.seed));
+	let context = Arc::new(Context::new(&options, physics_zero, None));
+
+	{
+		let context = context.clone();
+		thread::spawn(move || { spawn_coroutines(context, None); });
+	}
+
+	let mut input_frames_sent = 0;
+
+	while !context.exit.load(Ordering::Relaxed) {
+		let physics_frames = context.last_physics_frame.read().unwrap().0.frame_counter;
+		if physics_frames >= options.frames { break }
+
+		if input_frames_sent < physics_frames + MAX_INPUT_FRAMES_AHEAD {
+			if let Some(sender) = context.input_senders.try_pop() {
+				sender.send(()).unwrap();
+				input_frames_sent += 1;
+				continue;
+			}
+		}
+		thread::yield_now();
+	}
+
+	if options.screenshot.is_some() || options.golden.is_some() {
+		let physics_frame = context.last_physics_frame.read().unwrap().0.clone();
+		capture(&options, &context.physics, physics_frame);
+	}
+}
+
+// renders a frame offscreen with a headless GL context, no window or GPU needed
+//
+fn capture(options: &Options, physics: &PhysicsContext, physics_frame: Arc<PhysicsFrame>) {
+	const GOLDEN_TOLERANCE: u8 = 2; // software rasterizers round a little differently from each other
+
+	let glium_context = HeadlessRendererBuilder::new(HEADLESS_SIZE.0, HEADLESS_SIZE.1).build_glium().unwrap();
+
+	let q = Arc::new(MsQueue::new());
+	let render_context = RenderContext::new(&glium_context, q.clone(), &physics_frame.ik_chains, &physics_frame.bodies);
+	let render_processor = RenderProcessor::new(q, glium_context, HEADLESS_SIZE);
+
+	// no time passes headless, so draw the latest step itself rather than something on the way to it
+	//
+	let image = render_processor.render_to_image(RenderFrame::new(&render_context, physics, physics_frame, 1.0), HEADLESS_SIZE);
+
+	if let Some(ref path) = options.screenshot {
+		image.write(path).unwrap_or_else(|error| panic!("Could not write screenshot {}: {}", path, error));
+	}
+	if let Some(ref path) = options.golden {
+		let golden = Image::read_ppm(path).unwrap_or_else(|error| panic!("Could not read golden image {}: {}", path, error));
+
+		// stderr, stdout may be carrying the --dump
+		//
+		let mut stderr = io::stderr();
+		match image.mismatched_pixels(&golden, GOLDEN_TOLERANCE) {
+			Some(0) => { let _ = writeln!(stderr, "Matches golden image {}", path); },
+			Some(count) => {
+				let _ = writeln!(stderr, "{} pixels differ from golden image {}", count, path);
+				process::exit(1);
+			},
+			None => {
+				let _ = writeln!(stderr, "Golden image {} is {}x{}, expected {}x{}", path, golden.width, golden.height, image.width, image.height);
+				process::exit(1);
+			},
+		}
+	}
+}
+
 struct Continuation {
 	id: u64,
 	req_count: AtomicUsize,
@@ -127,22 +206,54 @@ enum Result { InputFrame(Arc<InputFrame>), PhysicsFrame(Arc<PhysicsFrame>) }
 
 pub struct Context {
 	exit: AtomicBool,
+	fullscreen: AtomicBool, // what the window should be, the event loop catches it up
 	input_senders: MsQueue<Sender<()>>,
-	last_physics_frame: RwLock<Arc<PhysicsFrame>>,
+	last_physics_frame: RwLock<(Arc<PhysicsFrame>, u64)>, // and when it was made, from time::precise_time_ns
 	physics_continuations: Arc<Mutex<HashMap<u64, Arc<Continuation>>>>,
 	render_tokens_length: AtomicUsize,
 
+	dump: Option<Mutex<Box<Write + Send>>>,
+
 	pub input: InputContext,
 	pub physics: PhysicsContext,
-	pub render: RenderContext,
+	pub render: Option<RenderContext>, // None when running headless
 }
 
 impl Context {
+	fn new(options: &Options, physics_zero: Arc<PhysicsFrame>, render: Option<RenderContext>) -> Context {
+		Context {
+			exit: AtomicBool::new(false),
+			fullscreen: AtomicBool::new(false),
+			input_senders: MsQueue::new(),
+			last_physics_frame: RwLock::new((physics_zero, time::precise_time_ns())),
+			render_tokens_length: AtomicUsize::new(0),
+			physics_continuations: Arc::new(Mutex::new(HashMap::new())),
+			dump: options.dump_writer().map(|writer| Mutex::new(writer)),
+
+			input:   InputContext::new(options.input_source(), options.input_map(), options.gamepad()),
+			physics: PhysicsContext::new().unwrap_or_else(|error| {
+				let _ = writeln!(io::stderr(), "{}, running with nothing to collide with", error);
+				PhysicsContext::empty()
+			}),
+			render:  render,
+		}
+	}
+
 	pub fn input_signal(&self) -> Receiver<()> {
 		let (sender, receiver) = channel();
 		self.input_senders.push(sender);
 		receiver
 	}
+
+	// stops the event loop, for quits that come from somewhere other than the window (e.g. a replay ending)
+	//
+	pub fn quit(&self) {
+		self.exit.store(true, Ordering::Relaxed);
+	}
+
+	pub fn toggle_fullscreen(&self) {
+		self.fullscreen.fetch_xor(true, Ordering::Relaxed);
+	}
 }
 
 fn input_entry(context: Arc<Context>, coroutine: Arc<Continuation>) {
@@ -203,14 +314,20 @@ fn physics_entry(context: Arc<Context>, coroutine: Arc<Continuation>) {
 		(input_frame, physics_frame)
 	};
 
-	let physics_frame = Arc::new(PhysicsFrame::new(context.clone(), last_physics_frame, last_input_frame));
+	let physics_frame = Arc::new(PhysicsFrame::new(&context.physics, last_physics_frame, last_input_frame));
 	let result = Arc::new(Result::PhysicsFrame(physics_frame.clone()));
 
+	if let Some(ref dump) = context.dump {
+		if let Err(error) = physics_frame.write_state(&mut *dump.lock().unwrap()) {
+			let _ = writeln!(io::stderr(), "Failed to dump physics state: {}", error);
+		}
+	}
+
 	{ // TODO: do something better (that doesnt potentially block the sender)
-		let latest_frame = context.last_physics_frame.read().unwrap().clone();
+		let latest_frame = context.last_physics_frame.read().unwrap().0.clone();
 		if physics_frame.frame_counter > latest_frame.frame_counter {
 			let mut reference = context.last_physics_frame.write().unwrap();
-			*reference = physics_frame.clone();
+			*reference = (physics_frame.clone(), time::precise_time_ns());
 		}
 	}
 
@@ -234,24 +351,25 @@ fn physics_entry(context: Arc<Context>, coroutine: Arc<Continuation>) {
 }
 
 fn render_entry(context: Arc<Context>, render_tokens: Receiver<RenderToken>) {
+	let render = context.render.as_ref().unwrap();
+
 	while !context.exit.load(Ordering::Relaxed) {
 		render_tokens.recv().unwrap();
 
-		let physics_frame = context.last_physics_frame.read().unwrap().clone();
+		let (physics_frame, made) = context.last_physics_frame.read().unwrap().clone();
 
 		// TODO: don't render the same physics_frame twice
 
-		let render_frame = {
-			let context = context.clone();
-			RenderFrame::new(context, physics_frame)
-		};
+		let since = time::precise_time_ns().saturating_sub(made) as f32 / 1e9;
+		let alpha = physics_frame.alpha(since);
+		let render_frame = RenderFrame::new(render, &context.physics, physics_frame, alpha);
 
-		context.render.q.push(render_frame);
+		render.q.push(render_frame);
 		context.render_tokens_length.fetch_sub(1, Ordering::Release);
 	}
 }
 
-fn spawn_coroutines(context: Arc<Context>, render_tokens: Receiver<RenderToken>) {
+fn spawn_coroutines(context: Arc<Context>, render_tokens: Option<Receiver<RenderToken>>) {
 	const NUM_THREADS: usize = 4;
 
 	let mut config = Config::new();
@@ -260,7 +378,7 @@ fn spawn_coroutines(context: Arc<Context>, render_tokens: Receiver<RenderToken>)
 	config.set_catch_panics(false);
 
 	{
-		let arc_physics_zero = context.last_physics_frame.read().unwrap().clone();
+		let arc_physics_zero = context.last_physics_frame.read().unwrap().0.clone();
 		let result = Arc::new(Result::PhysicsFrame(arc_physics_zero));
 		let q = MsQueue::new();
 		q.push(result);
@@ -284,7 +402,7 @@ fn spawn_coroutines(context: Arc<Context>, render_tokens: Receiver<RenderToken>)
 			let context = context.clone();
 			mioco::spawn(move||input_entry(context, continuation));
 		}
-		{ // for each render frame in flight
+		if let Some(render_tokens) = render_tokens { // for each render frame in flight
 			let context = context.clone();
 			mioco::spawn(move||render_entry(context, render_tokens));
 		}
@@ -1,3 +1,5 @@
 pub use self::context::{Context, init};
+pub use self::options::{Options};
 
 mod context;
+mod options;
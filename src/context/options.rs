@@ -0,0 +1,89 @@
+use std::env;
+use std::fs::{File};
+use std::io;
+use std::io::{Write};
+
+use input::{InputMap, InputSource, SyntheticGamepad};
+use physics::{DEFAULT_SEED};
+
+
+// --seed <n>           seeds the simulation, the same seed and inputs always play out the same way
+// --record <path>      writes every input event to path as it is consumed
+// --replay <path>      feeds a recording back in place of the keyboard and mouse
+// --headless           no window or GL, just input and physics (see context::run_headless)
+// --frames <n>         how many physics frames a headless run lasts
+// --dump <path>        writes one line of physics state per frame, - for stdout (the default when headless)
+// --bindings <path>    rebinds actions to keys, mouse buttons and gamepad axes (see input::input_map for the format)
+// --synthetic-gamepad  plugs in a scripted gamepad that walks the player in circles
+// --screenshot <path>  headless only, renders the last frame offscreen to a .png or .ppm
+// --golden <path>      headless only, renders the last frame and exits with 1 unless it matches a .ppm
+//
+pub struct Options {
+	pub seed:     usize,
+	pub record:   Option<String>,
+	pub replay:   Option<String>,
+	pub headless: bool,
+	pub frames:   u64,
+	pub dump:     Option<String>,
+
+	pub bindings:   Option<String>,
+	pub gamepad:    bool,
+	pub screenshot: Option<String>,
+	pub golden:     Option<String>,
+}
+
+impl Options {
+	pub fn from_args() -> Options {
+		let args: Vec<String> = env::args().collect();
+		let value = |flag: &str| args.windows(2).find(|pair| pair[0] == flag).map(|pair| pair[1].clone());
+
+		const DEFAULT_FRAMES: u64 = 600;
+
+		let headless = args.iter().any(|arg| arg == "--headless");
+
+		Options {
+			seed:     value("--seed").map_or(DEFAULT_SEED, |seed| seed.parse().unwrap_or_else(|error| panic!("Could not parse --seed {}: {}", seed, error))),
+			record:   value("--record"),
+			replay:   value("--replay"),
+			headless: headless,
+			frames:   value("--frames").map_or(DEFAULT_FRAMES, |frames| frames.parse().unwrap_or_else(|error| panic!("Could not parse --frames {}: {}", frames, error))),
+			dump:     value("--dump").or(if headless { Some(format!("-")) } else { None }),
+
+			bindings:   value("--bindings"),
+			gamepad:    args.iter().any(|arg| arg == "--synthetic-gamepad"),
+			screenshot: value("--screenshot"),
+			golden:     value("--golden"),
+		}
+	}
+
+	pub fn dump_writer(&self) -> Option<Box<Write + Send>> {
+		self.dump.as_ref().map(|path| {
+			if path == "-" {
+				Box::new(io::stdout()) as Box<Write + Send>
+			} else {
+				Box::new(File::create(path).unwrap_or_else(|error| panic!("Could not create dump {}: {}", path, error))) as Box<Write + Send>
+			}
+		})
+	}
+
+	pub fn input_map(&self) -> InputMap {
+		match self.bindings {
+			Some(ref path) => InputMap::load(path).unwrap_or_else(|error| panic!("Could not load bindings {}: {}", path, error)),
+			None           => InputMap::default(),
+		}
+	}
+
+	pub fn gamepad(&self) -> Option<SyntheticGamepad> {
+		if self.gamepad { Some(SyntheticGamepad::circling()) } else { None }
+	}
+
+	pub fn input_source(&self) -> InputSource {
+		if let Some(ref path) = self.replay {
+			InputSource::playback(path).unwrap_or_else(|error| panic!("Could not load input recording {}: {}", path, error))
+		} else if let Some(ref path) = self.record {
+			InputSource::recording(path).unwrap_or_else(|error| panic!("Could not create input recording {}: {}", path, error))
+		} else {
+			InputSource::Live
+		}
+	}
+}
@@ -6,9 +6,8 @@ use unlit_model::{UnlitModel};
 use render::vertices::{UnlitVertex};
 
 
-pub fn model<F: Facade>(facade: &F) -> UnlitModel {
+pub fn model<F: Facade>(facade: &F, color: [f32;3]) -> UnlitModel {
 	const S: f32 = 0.3;
-	let color: [f32;3] = [0.2, 0.8, 0.2];
 	let vertices = vec![
 		UnlitVertex { position: [ 0.0,   -S,  0.0], color: color },
 		UnlitVertex { position: [ 0.0,    S,  0.0], color: color },
@@ -0,0 +1,44 @@
+use std::collections::{HashMap, HashSet};
+
+use input::gamepad::{AXES, Axis};
+use input::input_map::{Action, Binding, InputMap};
+
+
+// the keys and mouse buttons currently held down and where each axis currently sits
+//
+#[derive(Clone, Default)]
+pub struct ControlState {
+	held: HashSet<Binding>,
+	axes: HashMap<Axis, f32>,
+}
+
+impl ControlState {
+	pub fn set(&mut self, binding: Binding, pressed: bool) {
+		if pressed { self.held.insert(binding); } else { self.held.remove(&binding); }
+	}
+
+	pub fn set_axis(&mut self, axis: Axis, value: f32) {
+		self.axes.insert(axis, value);
+	}
+
+	// how strongly an action is held, from 0 to 1, keys and buttons are all or nothing
+	// while axes count for however far they're pushed (through the dead zone and curve)
+	//
+	pub fn value(&self, input_map: &InputMap, action: Action) -> f32 {
+		let buttons = if self.held.iter().any(|&binding| input_map.action(binding) == Some(action)) { 1.0 } else { 0.0 };
+
+		AXES.iter().fold(buttons, |value, &axis| {
+			let shaped = self.shaped_axis(input_map, axis);
+			let half = if shaped > 0.0 { Binding::AxisPositive(axis) } else { Binding::AxisNegative(axis) };
+
+			if shaped != 0.0 && input_map.action(half) == Some(action) { value.max(shaped.abs()) } else { value }
+		})
+	}
+
+	fn shaped_axis(&self, input_map: &InputMap, axis: Axis) -> f32 {
+		let position = |axis| self.axes.get(&axis).cloned().unwrap_or(0.0);
+		let partner = axis.partner().map_or(0.0, &position);
+
+		input_map.axis_settings.shape(position(axis), partner)
+	}
+}
@@ -0,0 +1,203 @@
+use std::f32::consts::{PI};
+
+use input::input_event::{InputEvent};
+
+
+// sticks run from -1 to 1 with up and right positive, triggers from 0 (released) to 1
+//
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Axis {
+	LeftStickX,
+	LeftStickY,
+	RightStickX,
+	RightStickY,
+	LeftTrigger,
+	RightTrigger,
+}
+
+pub const AXES: [Axis; 6] = [
+	Axis::LeftStickX,
+	Axis::LeftStickY,
+	Axis::RightStickX,
+	Axis::RightStickY,
+	Axis::LeftTrigger,
+	Axis::RightTrigger,
+];
+
+impl Axis {
+	pub fn name(&self) -> String {
+		format!("{:?}", self)
+	}
+
+	pub fn from_name(name: &str) -> Option<Axis> {
+		AXES.iter().find(|axis| axis.name() == name).cloned()
+	}
+
+	// the other half of a stick, dead zones are applied to the stick as a whole so
+	// diagonals aren't clipped into a cross
+	//
+	pub fn partner(&self) -> Option<Axis> {
+		match *self {
+			Axis::LeftStickX  => Some(Axis::LeftStickY),
+			Axis::LeftStickY  => Some(Axis::LeftStickX),
+			Axis::RightStickX => Some(Axis::RightStickY),
+			Axis::RightStickY => Some(Axis::RightStickX),
+			_                 => None,
+		}
+	}
+}
+
+// how far the stick is pushed (after the dead zone) to how much it counts for
+//
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ResponseCurve {
+	Linear,
+	// higher exponents give finer control near the centre, 2 and 3 are the usual choices
+	Power(f32),
+}
+
+impl ResponseCurve {
+	pub fn from_name(name: &str) -> Option<ResponseCurve> {
+		match name {
+			"linear"    => Some(ResponseCurve::Linear),
+			"quadratic" => Some(ResponseCurve::Power(2.0)),
+			"cubic"     => Some(ResponseCurve::Power(3.0)),
+			// anything at or below 0 (or NaN) sends the smallest push to full deflection or beyond
+			//
+			_ => name.parse().ok().and_then(|exponent: f32| {
+				if exponent.is_finite() && exponent > 0.0 { Some(ResponseCurve::Power(exponent)) } else { None }
+			}),
+		}
+	}
+
+	pub fn apply(&self, t: f32) -> f32 {
+		match *self {
+			ResponseCurve::Linear          => t,
+			ResponseCurve::Power(exponent) => t.powf(exponent),
+		}
+	}
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct AxisSettings {
+	pub dead_zone: f32,
+	pub curve:     ResponseCurve,
+}
+
+impl Default for AxisSettings {
+	fn default() -> AxisSettings {
+		AxisSettings {
+			dead_zone: 0.15,
+			curve:     ResponseCurve::Power(2.0),
+		}
+	}
+}
+
+impl AxisSettings {
+	// `value` with the dead zone cut out and the curve applied, `partner` is the other half
+	// of its stick (0 for triggers), the result still runs from -1 to 1
+	//
+	pub fn shape(&self, value: f32, partner: f32) -> f32 {
+		let magnitude = (value * value + partner * partner).sqrt();
+		if magnitude <= self.dead_zone { return 0.0 }
+
+		let past_dead_zone = (magnitude.min(1.0) - self.dead_zone) / (1.0 - self.dead_zone);
+
+		value / magnitude * self.curve.apply(past_dead_zone)
+	}
+}
+
+// stands in for a real pad, which glutin doesn't expose, by playing a script of stick and
+// trigger positions against the input frame counter, only sending the axes that moved
+//
+pub struct SyntheticGamepad {
+	script: fn(u64) -> [f32; 6],
+	last:   [f32; 6],
+}
+
+impl SyntheticGamepad {
+	pub fn new(script: fn(u64) -> [f32; 6]) -> SyntheticGamepad {
+		SyntheticGamepad {
+			script: script,
+			last:   [0.0; 6],
+		}
+	}
+
+	// walks the player around in a slow circle, easing the left stick in and out of the dead zone
+	// while the right stick pans the camera
+	//
+	pub fn circling() -> SyntheticGamepad {
+		fn script(frame: u64) -> [f32; 6] {
+			const PERIOD: f32 = 600.0;
+
+			let angle = 2.0 * PI * (frame as f32) / PERIOD;
+			let push = 0.5 - 0.5 * (2.0 * angle).cos();
+
+			[push * angle.sin(), push * angle.cos(), 0.4, 0.0, 0.0, 0.0]
+		}
+		SyntheticGamepad::new(script)
+	}
+
+	pub fn poll(&mut self, frame: u64) -> Vec<InputEvent> {
+		let values = (self.script)(frame);
+
+		let mut events = Vec::new();
+		for (index, &axis) in AXES.iter().enumerate() {
+			if values[index] != self.last[index] {
+				events.push(InputEvent::AxisMoved{ axis: axis, value: values[index] });
+			}
+		}
+		self.last = values;
+		events
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use input::control_state::{ControlState};
+	use input::input_event::{InputEvent};
+	use input::input_map::{Action, InputMap};
+	use super::{Axis, AxisSettings, ResponseCurve, SyntheticGamepad};
+
+
+	#[test]
+	fn dead_zone_and_curve_shape_the_stick() {
+		let settings = AxisSettings { dead_zone: 0.2, curve: ResponseCurve::Power(2.0) };
+
+		assert_eq!(settings.shape(0.15, 0.0), 0.0);
+		assert_eq!(settings.shape(-1.0, 0.0), -1.0);
+		assert!((settings.shape(0.6, 0.0) - 0.25).abs() < 1e-6);
+
+		// the dead zone is round, so a diagonal inside it on both axes still moves once it leaves the circle
+		let diagonal = settings.shape(0.18, 0.18);
+		assert!(diagonal > 0.0 && diagonal < 0.18);
+	}
+
+	#[test]
+	fn half_a_stick_moves_slower_than_a_key() {
+		fn script(frame: u64) -> [f32; 6] {
+			[0.0, if frame < 10 { 0.6 } else { 0.0 }, 0.0, 0.0, 0.0, 0.0]
+		}
+		fn apply(state: &mut ControlState, events: Vec<InputEvent>) {
+			for event in events {
+				if let InputEvent::AxisMoved{ axis, value } = event { state.set_axis(axis, value) }
+			}
+		}
+		let mut gamepad = SyntheticGamepad::new(script);
+		let map = InputMap::default();
+		let mut state = ControlState::default();
+
+		let events = gamepad.poll(1);
+		assert_eq!(events, vec![InputEvent::AxisMoved{ axis: Axis::LeftStickY, value: 0.6 }]);
+		apply(&mut state, events);
+
+		let forward = state.value(&map, Action::MoveForward);
+		assert!(forward > 0.0 && forward < 1.0, "forward was {}", forward);
+		assert_eq!(state.value(&map, Action::MoveBackward), 0.0);
+
+		assert_eq!(gamepad.poll(2).len(), 0);
+
+		apply(&mut state, gamepad.poll(10));
+		assert_eq!(state.value(&map, Action::MoveForward), 0.0);
+	}
+}
@@ -1,19 +1,45 @@
+use std::io;
+use std::io::{Write};
+use std::sync::{Mutex};
+
 use crossbeam::sync::{MsQueue};
 
+use input::gamepad::{SyntheticGamepad};
 use input::input_event::{InputEvent};
-use input::input_map::{InputMap};
+use input::input_map::{Action, Binding, InputMap};
+use input::input_recording::{InputPlayback, InputRecorder};
+
+
+pub enum InputSource {
+	Live,
+	Recording(Mutex<InputRecorder>),
+	Playback(Mutex<InputPlayback>),
+}
 
+impl InputSource {
+	pub fn recording(path: &str) -> io::Result<InputSource> {
+		InputRecorder::create(path).map(|recorder| InputSource::Recording(Mutex::new(recorder)))
+	}
+
+	pub fn playback(path: &str) -> io::Result<InputSource> {
+		InputPlayback::load(path).map(|playback| InputSource::Playback(Mutex::new(playback)))
+	}
+}
 
 pub struct InputContext {
 	pub input_q:   MsQueue<InputEvent>,
 	pub input_map: InputMap,
+	pub source:    InputSource,
+	pub gamepad:   Option<Mutex<SyntheticGamepad>>,
 }
 
 impl InputContext {
-	pub fn new() -> InputContext {
+	pub fn new(source: InputSource, input_map: InputMap, gamepad: Option<SyntheticGamepad>) -> InputContext {
 		InputContext {
 			input_q:   MsQueue::new(),
-			input_map: InputMap{},
+			input_map: input_map,
+			source:    source,
+			gamepad:   gamepad.map(Mutex::new),
 		}
 	}
 
@@ -22,6 +48,41 @@ impl InputContext {
 	pub fn post_input_events<T: IntoIterator<Item=InputEvent>>(&self, input_events: T) {
 		for event in input_events { self.input_q.push(event) }
 	}
+
+	// the events input frame `frame` should act on, live events are thrown away during playback
+	// apart from quitting (or pressing something bound to quit) so a replay can still be cut short,
+	// and resizes since they describe the window the replay is actually being shown in
+	//
+	pub fn take_events(&self, frame: u64) -> Vec<InputEvent> {
+		let mut events = Vec::new();
+		while let Some(event) = self.input_q.try_pop() { events.push(event) }
+		if let Some(ref gamepad) = self.gamepad { events.extend(gamepad.lock().unwrap().poll(frame)) }
+
+		match self.source {
+			InputSource::Live => events,
+			InputSource::Recording(ref recorder) => {
+				if let Err(error) = recorder.lock().unwrap().record(frame, &events) {
+					let _ = writeln!(io::stderr(), "Failed to record input: {}", error);
+				}
+				events
+			},
+			InputSource::Playback(ref playback) => {
+				let mut replayed = playback.lock().unwrap().take(frame);
+				replayed.extend(events.into_iter().filter(|event| self.passes_playback(event)));
+				replayed
+			},
+		}
+	}
+
+	fn passes_playback(&self, event: &InputEvent) -> bool {
+		match *event {
+			InputEvent::Quit                                => true,
+			InputEvent::Resized{ .. }                       => true,
+			InputEvent::KeyboardInput{ pressed: true, id }  => self.input_map.action(Binding::Key(id)) == Some(Action::Quit),
+			InputEvent::MouseInput{ pressed: true, button } => self.input_map.action(Binding::MouseButton(button)) == Some(Action::Quit),
+			_                                               => false,
+		}
+	}
 }
 
 unsafe impl Send for InputContext {}
@@ -1,14 +1,29 @@
-use glium::glutin::{VirtualKeyCode};
+use glium::glutin::{MouseButton, VirtualKeyCode};
 
+use input::gamepad::{Axis};
 
+
+#[derive(Clone, PartialEq, Debug)]
 pub enum InputEvent {
 	Quit,
 	KeyboardInput {
 		pressed: bool,
 		id: VirtualKeyCode,
 	},
+	MouseInput {
+		pressed: bool,
+		button: MouseButton,
+	},
 	MouseMoved {
 		dx: f32,
 		dy: f32,
 	},
+	AxisMoved {
+		axis: Axis,
+		value: f32,
+	},
+	Resized {
+		width: u32,
+		height: u32,
+	},
 }
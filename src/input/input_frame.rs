@@ -3,67 +3,130 @@ use std::sync::{Arc};
 use cgmath::{Vector2};
 use mioco;
 
-use input::keyboard_state::{KeyboardState};
+use input::control_state::{ControlState};
 use input::input_event::{InputEvent};
+use input::input_map::{Action, Binding};
 use context::{Context};
 
 
+// input frames are kicked at this rate on the gl thread, physics steps at its own (see PhysicsFrame::new)
+//
+pub const INPUT_FREQUENCY: u64 = 120;
+
 #[derive(Clone)]
 pub struct InputFrame {
 	pub frame_counter: u64,
+	pub elapsed: f32, // seconds since the last input frame
 	pub movement_delta: Vector2<f32>,
 	pub view_angles_delta: Vector2<f32>,
-	pub keyboard_state: KeyboardState,
+	pub smoothed_mouse: Vector2<f32>, // pixels, see MouseSettings::smooth
+	pub resized: Option<(u32, u32)>, // the window's new size if it changed this frame
+	pub jump: bool, // pressed this frame, holding it doesn't jump again
+	pub pick: bool, // same, for whatever's under the crosshair
+	pub control_state: ControlState,
 }
 
 impl InputFrame {
 	pub fn frame_zero() -> InputFrame {
 		InputFrame {
 			frame_counter:     0,
+			elapsed:           0.0,
 			movement_delta:    Vector2::new(0.0, 0.0),
 			view_angles_delta: Vector2::new(0.0, 0.0),
-			keyboard_state:    Default::default(),
+			smoothed_mouse:    Vector2::new(0.0, 0.0),
+			resized:           None,
+			jump:              false,
+			pick:              false,
+			control_state:     Default::default(),
 		}
 	}
 
 	pub fn new(context: Arc<Context>, frame: Arc<InputFrame>) -> InputFrame {
 		let ic = &context.input;
 
-		let mut keyboard_state = frame.keyboard_state.clone();
+		let mut control_state = frame.control_state.clone();
 		let mut mouse_movement = Vector2::new(0f32, 0f32);
+		let mut resized = None;
+		let mut jump = false;
+		let mut pick = false;
 
-		while let Some(event) = ic.input_q.try_pop() {
-			match event {
-				InputEvent::KeyboardInput{ pressed, id } => {
-					if id == ic.input_map.forward()  { keyboard_state.forward  = pressed }
-					if id == ic.input_map.backward() { keyboard_state.backward = pressed }
-					if id == ic.input_map.right()    { keyboard_state.right    = pressed }
-					if id == ic.input_map.left()     { keyboard_state.left     = pressed }
-				},
+		let frame_counter = frame.frame_counter + 1;
+
+		for event in ic.take_events(frame_counter) {
+			let (binding, pressed) = match event {
+				InputEvent::KeyboardInput{ pressed, id }  => (Binding::Key(id), pressed),
+				InputEvent::MouseInput{ pressed, button } => (Binding::MouseButton(button), pressed),
 				InputEvent::MouseMoved{ dx, dy } => {
-					mouse_movement = mouse_movement + Vector2::new(dx, dy);
+					if ic.input_map.action(Binding::MouseMotion) == Some(Action::Look) {
+						mouse_movement = mouse_movement + Vector2::new(dx, dy);
+					}
+					continue;
 				},
-				InputEvent::Quit => {
-					mioco::shutdown();
+				InputEvent::AxisMoved{ axis, value } => {
+					control_state.set_axis(axis, value);
+					continue;
 				},
+				InputEvent::Resized{ width, height } => {
+					resized = Some((width, height));
+					continue;
+				},
+				InputEvent::Quit => quit(&context),
+			};
+
+			control_state.set(binding, pressed);
+
+			match (pressed, ic.input_map.action(binding)) {
+				(true, Some(Action::Quit))             => quit(&context),
+				(true, Some(Action::Jump))             => jump = true,
+				(true, Some(Action::Pick))             => pick = true,
+				(true, Some(Action::ToggleFullscreen)) => context.toggle_fullscreen(),
+				_                                      => (),
 			}
 		}
 
 		const FORWARD: Vector2<f32> = Vector2{ x: 1f32, y: 0f32};
 		const RIGHT:   Vector2<f32> = Vector2{ x: 0f32, y: 1f32};
 
-		let mut direction = Vector2::new(0f32, 0f32);
+		// held look actions (sticks or keys) turn the camera this many radians per frame
+		//
+		const LOOK_RATE: f32 = 0.02;
 
-		if keyboard_state.forward  { direction = direction + FORWARD }
-		if keyboard_state.backward { direction = direction - FORWARD }
-		if keyboard_state.right    { direction = direction + RIGHT   }
-		if keyboard_state.left     { direction = direction - RIGHT   }
+		// analog bindings make these continuous, half a stick is half speed
+		//
+		let (direction, look) = {
+			let value = |action| control_state.value(&ic.input_map, action);
+
+			let direction
+				= FORWARD * (value(Action::MoveForward) - value(Action::MoveBackward))
+				+ RIGHT   * (value(Action::MoveRight)   - value(Action::MoveLeft));
+
+			let look = Vector2::new(
+				value(Action::LookRight) - value(Action::LookLeft),
+				value(Action::LookDown)  - value(Action::LookUp)
+			) * LOOK_RATE;
+
+			(direction, look)
+		};
+
+		let mouse_settings = &ic.input_map.mouse_settings;
+		let smoothed_mouse = mouse_settings.smooth(mouse_movement, frame.smoothed_mouse);
 
 		InputFrame {
-			frame_counter: frame.frame_counter + 1,
+			frame_counter: frame_counter,
+			// nominal rather than measured, so recordings and headless runs step physics the same way
+			elapsed: 1.0 / (INPUT_FREQUENCY as f32),
 			movement_delta: direction,
-			view_angles_delta: mouse_movement,
-			keyboard_state: keyboard_state,
+			view_angles_delta: mouse_settings.look(smoothed_mouse) + look,
+			smoothed_mouse: smoothed_mouse,
+			resized: resized,
+			jump: jump,
+			pick: pick,
+			control_state: control_state,
 		}
 	}
 }
+
+fn quit(context: &Context) -> ! {
+	context.quit();
+	mioco::shutdown();
+}
@@ -1,11 +1,378 @@
-use glium::glutin::{VirtualKeyCode};
+use std::collections::{HashMap, HashSet};
+use std::fs::{File};
+use std::io;
+use std::io::{Read};
 
+use glium::glutin::{MouseButton, VirtualKeyCode};
 
-pub struct InputMap {}
+use input::gamepad::{Axis, AxisSettings, ResponseCurve};
+use input::key_names::{button_from_name, key_from_name};
+#[cfg(test)]
+use input::key_names::{button_name, key_name};
+use input::mouse::{MouseSettings};
+
+
+// --- BINDINGS FILE ---
+// a small subset of TOML, every action listed replaces its default bindings,
+// actions that are left out keep theirs
+//
+// [bindings]
+// move_forward = ["W", "Up"]               # key names as in key_names.rs
+// look         = "MouseMotion"              # the mouse itself, or [] to turn mouse look off
+// quit         = ["Escape", "MouseMiddle"]  # mouse buttons are Mouse + Left, Right, Middle, Button4, ...
+// look_up      = ["RightStickY+", "Up"]     # gamepad axes are split into + and - halves, see gamepad.rs
+//
+// [axes]
+// dead_zone = 0.2       # how far a stick or trigger moves before it counts
+// curve     = "cubic"   # linear, quadratic, cubic or any positive exponent
+//
+// [mouse]
+// sensitivity  = 0.002   # radians per pixel
+// invert_y     = true
+// smoothing    = 0.5     # 0 is off, up to but not including 1
+// acceleration = 0.05    # extra sensitivity per pixel per frame of speed
+//
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+	MoveForward,
+	MoveBackward,
+	MoveRight,
+	MoveLeft,
+	Look,
+	LookLeft,
+	LookRight,
+	LookUp,
+	LookDown,
+	Jump,
+	Pick,
+	ToggleFullscreen,
+	Quit,
+}
+
+const ACTIONS: [(Action, &'static str); 13] = [
+	(Action::MoveForward,      "move_forward"),
+	(Action::MoveBackward,     "move_backward"),
+	(Action::MoveRight,        "move_right"),
+	(Action::MoveLeft,         "move_left"),
+	(Action::Look,             "look"),
+	(Action::LookLeft,         "look_left"),
+	(Action::LookRight,        "look_right"),
+	(Action::LookUp,           "look_up"),
+	(Action::LookDown,         "look_down"),
+	(Action::Jump,             "jump"),
+	(Action::Pick,             "pick"),
+	(Action::ToggleFullscreen, "toggle_fullscreen"),
+	(Action::Quit,             "quit"),
+];
+
+impl Action {
+	pub fn name(&self) -> &'static str {
+		ACTIONS.iter().find(|&&(action, _)| action == *self).unwrap().1
+	}
+
+	pub fn from_name(name: &str) -> Option<Action> {
+		ACTIONS.iter().find(|&&(_, action_name)| action_name == name).map(|&(action, _)| action)
+	}
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Binding {
+	Key(VirtualKeyCode),
+	MouseButton(MouseButton),
+	MouseMotion,
+	AxisPositive(Axis),
+	AxisNegative(Axis),
+}
+
+impl Binding {
+	#[cfg(test)]
+	pub fn name(&self) -> String {
+		match *self {
+			Binding::Key(key)            => key_name(key),
+			Binding::MouseButton(button) => format!("Mouse{}", button_name(button)),
+			Binding::MouseMotion         => format!("MouseMotion"),
+			Binding::AxisPositive(axis)  => format!("{}+", axis.name()),
+			Binding::AxisNegative(axis)  => format!("{}-", axis.name()),
+		}
+	}
+
+	// no key name starts with Mouse so the two can't be confused
+	//
+	pub fn from_name(name: &str) -> Option<Binding> {
+		if name == "MouseMotion" {
+			Some(Binding::MouseMotion)
+		} else if name.ends_with('+') {
+			Axis::from_name(&name[..name.len() - 1]).map(Binding::AxisPositive)
+		} else if name.ends_with('-') {
+			Axis::from_name(&name[..name.len() - 1]).map(Binding::AxisNegative)
+		} else if name.starts_with("Mouse") {
+			button_from_name(&name["Mouse".len()..]).map(Binding::MouseButton)
+		} else {
+			key_from_name(name).map(Binding::Key)
+		}
+	}
+}
+
+pub struct InputMap {
+	bindings: HashMap<Binding, Action>,
+
+	pub axis_settings:  AxisSettings,
+	pub mouse_settings: MouseSettings,
+}
+
+impl Default for InputMap {
+	fn default() -> InputMap {
+		let defaults = [
+			(Binding::Key(VirtualKeyCode::W),         Action::MoveForward),
+			(Binding::Key(VirtualKeyCode::S),         Action::MoveBackward),
+			(Binding::Key(VirtualKeyCode::D),         Action::MoveRight),
+			(Binding::Key(VirtualKeyCode::A),         Action::MoveLeft),
+			(Binding::MouseMotion,                    Action::Look),
+			(Binding::Key(VirtualKeyCode::Space),     Action::Jump),
+			(Binding::MouseButton(MouseButton::Left), Action::Pick),
+			(Binding::Key(VirtualKeyCode::F11),       Action::ToggleFullscreen),
+			(Binding::Key(VirtualKeyCode::Escape),    Action::Quit),
+
+			(Binding::AxisPositive(Axis::LeftStickY),  Action::MoveForward),
+			(Binding::AxisNegative(Axis::LeftStickY),  Action::MoveBackward),
+			(Binding::AxisPositive(Axis::LeftStickX),  Action::MoveRight),
+			(Binding::AxisNegative(Axis::LeftStickX),  Action::MoveLeft),
+			(Binding::AxisNegative(Axis::RightStickX), Action::LookLeft),
+			(Binding::AxisPositive(Axis::RightStickX), Action::LookRight),
+			(Binding::AxisPositive(Axis::RightStickY), Action::LookUp),
+			(Binding::AxisNegative(Axis::RightStickY), Action::LookDown),
+		];
+		InputMap {
+			bindings:       defaults.iter().cloned().collect(),
+			axis_settings:  Default::default(),
+			mouse_settings: Default::default(),
+		}
+	}
+}
 
 impl InputMap {
-	pub fn forward(&self)  -> VirtualKeyCode { VirtualKeyCode::W }
-	pub fn backward(&self) -> VirtualKeyCode { VirtualKeyCode::S }
-	pub fn right(&self)    -> VirtualKeyCode { VirtualKeyCode::D }
-	pub fn left(&self)     -> VirtualKeyCode { VirtualKeyCode::A }
+	pub fn load(path: &str) -> io::Result<InputMap> {
+		let mut text = String::new();
+		try!(try!(File::open(path)).read_to_string(&mut text));
+
+		InputMap::parse(&text).map_err(|message| io::Error::new(io::ErrorKind::InvalidData, format!("{}:{}", path, message)))
+	}
+
+	pub fn parse(text: &str) -> Result<InputMap, String> {
+		let mut overrides: HashMap<Binding, Action> = HashMap::new();
+		let mut replaced = HashSet::new();
+		let mut axis_settings = AxisSettings::default();
+		let mut mouse_settings = MouseSettings::default();
+
+		let mut section = String::new();
+		for (number, line) in text.lines().enumerate() {
+			let error = |message: String| format!("{}: {}", number + 1, message);
+
+			let line = strip_comment(line).trim();
+			if line.is_empty() { continue }
+
+			if line.starts_with('[') && line.ends_with(']') {
+				section = line[1..line.len() - 1].trim().to_string();
+				if !SECTIONS.contains(&&section[..]) { return Err(error(format!("unknown section [{}]", section))) }
+				continue;
+			}
+
+			let (name, value) = match line.find('=') {
+				Some(index) => (line[..index].trim(), line[index + 1..].trim()),
+				None        => return Err(error(format!("expected name = value, found \"{}\"", line))),
+			};
+
+			if section == "axes" {
+				let setting = value.trim_matches('"');
+				match (name, setting.parse::<f32>().ok(), ResponseCurve::from_name(setting)) {
+					("dead_zone", Some(dead_zone), _) if 0.0 <= dead_zone && dead_zone < 1.0 => axis_settings.dead_zone = dead_zone,
+					("curve", _, Some(curve)) => axis_settings.curve = curve,
+					_ => return Err(error(format!("bad axis setting \"{}\"", line))),
+				}
+				continue;
+			}
+			if section == "mouse" {
+				match (name, value.parse::<f32>().ok(), value.parse::<bool>().ok()) {
+					("sensitivity", Some(sensitivity), _) if sensitivity > 0.0 => mouse_settings.sensitivity = sensitivity,
+					("invert_y", _, Some(invert_y)) => mouse_settings.invert_y = invert_y,
+					("smoothing", Some(smoothing), _) if 0.0 <= smoothing && smoothing < 1.0 => mouse_settings.smoothing = smoothing,
+					("acceleration", Some(acceleration), _) if acceleration >= 0.0 => mouse_settings.acceleration = acceleration,
+					_ => return Err(error(format!("bad mouse setting \"{}\"", line))),
+				}
+				continue;
+			}
+			if section != "bindings" { return Err(error(format!("expected a section before \"{}\"", line))) }
+
+			let action = match Action::from_name(name) {
+				Some(action) => action,
+				None         => return Err(error(format!("unknown action \"{}\"", name))),
+			};
+			if !replaced.insert(action) { return Err(error(format!("{} is listed twice", name))) }
+
+			for binding_name in try!(parse_strings(value).map_err(&error)) {
+				let binding = match Binding::from_name(&binding_name) {
+					Some(binding) => binding,
+					None          => return Err(error(format!("unknown key or button \"{}\"", binding_name))),
+				};
+				if let Some(other) = overrides.insert(binding, action) {
+					return Err(error(format!("{} is bound to both {} and {}", binding_name, other.name(), name)));
+				}
+			}
+		}
+
+		let mut bindings: HashMap<Binding, Action> = InputMap::default().bindings.into_iter()
+			.filter(|&(binding, action)| !replaced.contains(&action) && !overrides.contains_key(&binding))
+			.collect();
+		bindings.extend(overrides);
+		Ok(InputMap {
+			bindings:       bindings,
+			axis_settings:  axis_settings,
+			mouse_settings: mouse_settings,
+		})
+	}
+
+	pub fn action(&self, binding: Binding) -> Option<Action> {
+		self.bindings.get(&binding).cloned()
+	}
+}
+
+const SECTIONS: [&'static str; 3] = ["bindings", "axes", "mouse"];
+
+fn strip_comment(line: &str) -> &str {
+	let mut quoted = false;
+	for (index, character) in line.char_indices() {
+		match character {
+			'"'            => quoted = !quoted,
+			'#' if !quoted => return &line[..index],
+			_              => (),
+		}
+	}
+	line
+}
+
+// "W" or ["W", "Up"]
+//
+fn parse_strings(value: &str) -> Result<Vec<String>, String> {
+	let items: Vec<&str> = if value.starts_with('[') && value.ends_with(']') {
+		value[1..value.len() - 1].split(',').map(|item| item.trim()).filter(|item| !item.is_empty()).collect()
+	} else {
+		vec![value]
+	};
+
+	items.into_iter().map(|item| {
+		if item.len() >= 2 && item.starts_with('"') && item.ends_with('"') {
+			Ok(item[1..item.len() - 1].to_string())
+		} else {
+			Err(format!("expected a quoted name, found {}", item))
+		}
+	}).collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use glium::glutin::{MouseButton, VirtualKeyCode};
+
+	use input::control_state::{ControlState};
+	use input::gamepad::{Axis, ResponseCurve};
+	use super::{Action, Binding, InputMap};
+
+
+	#[test]
+	fn listed_actions_replace_their_defaults() {
+		let map = InputMap::parse("
+			# arrows instead of WASD for going forward
+			[bindings]
+			move_forward = [\"Up\", \"Numpad8\"]
+			quit = \"MouseRight\" # comment
+		").unwrap();
+
+		assert_eq!(map.action(Binding::Key(VirtualKeyCode::Up)),         Some(Action::MoveForward));
+		assert_eq!(map.action(Binding::Key(VirtualKeyCode::Numpad8)),    Some(Action::MoveForward));
+		assert_eq!(map.action(Binding::Key(VirtualKeyCode::W)),          None);
+		assert_eq!(map.action(Binding::Key(VirtualKeyCode::S)),          Some(Action::MoveBackward));
+		assert_eq!(map.action(Binding::MouseButton(MouseButton::Right)), Some(Action::Quit));
+		assert_eq!(map.action(Binding::Key(VirtualKeyCode::Escape)),     None);
+		assert_eq!(map.action(Binding::MouseMotion),                     Some(Action::Look));
+		assert_eq!(map.action(Binding::MouseButton(MouseButton::Left)),  Some(Action::Pick));
+	}
+
+	#[test]
+	fn rebinding_a_default_key_takes_it_from_its_old_action() {
+		let map = InputMap::parse("[bindings]\nquit = \"W\"").unwrap();
+
+		assert_eq!(map.action(Binding::Key(VirtualKeyCode::W)),         Some(Action::Quit));
+		assert_eq!(map.action(Binding::AxisPositive(Axis::LeftStickY)), Some(Action::MoveForward));
+	}
+
+	#[test]
+	fn bad_files_are_rejected() {
+		let files = [
+			"move_forward = \"W\"",
+			"[keys]",
+			"[bindings]\nfly = \"Space\"",
+			"[bindings]\nquit = \"Nope\"",
+			"[bindings]\nquit = Escape",
+			"[bindings]\nquit = \"Q\"\nquit = \"E\"",
+			"[bindings]\nquit = \"Q\"\nlook = \"Q\"",
+			"[bindings]\nquit = \"Sideways+\"",
+			"[axes]\ndead_zone = 1.5",
+			"[axes]\ncurve = \"wobbly\"",
+			"[axes]\ncurve = \"-1\"",
+			"[axes]\ncurve = \"0\"",
+			"[axes]\ncurve = \"NaN\"",
+			"[mouse]\nsmoothing = 1",
+			"[mouse]\ninvert_y = yes",
+		];
+		for file in &files {
+			assert!(InputMap::parse(file).is_err(), "accepted {:?}", file);
+		}
+	}
+
+	#[test]
+	fn every_binding_name_round_trips() {
+		let bindings = [
+			Binding::Key(VirtualKeyCode::LShift),
+			Binding::MouseButton(MouseButton::Other(5)),
+			Binding::MouseMotion,
+			Binding::AxisPositive(Axis::RightTrigger),
+			Binding::AxisNegative(Axis::LeftStickX),
+		];
+		for binding in &bindings {
+			assert_eq!(Binding::from_name(&binding.name()), Some(*binding));
+		}
+	}
+
+	#[test]
+	fn actions_stay_held_until_all_their_bindings_are_released() {
+		let map = InputMap::parse("[bindings]\nmove_left = [\"A\", \"Left\"]").unwrap();
+		let mut held = ControlState::default();
+
+		held.set(Binding::Key(VirtualKeyCode::A), true);
+		held.set(Binding::Key(VirtualKeyCode::Left), true);
+		held.set(Binding::Key(VirtualKeyCode::A), false);
+		assert_eq!(held.value(&map, Action::MoveLeft), 1.0);
+
+		held.set(Binding::Key(VirtualKeyCode::Left), false);
+		assert_eq!(held.value(&map, Action::MoveLeft), 0.0);
+	}
+
+	#[test]
+	fn axis_settings_are_read_from_their_own_section() {
+		let map = InputMap::parse("[axes]\ndead_zone = 0.25\ncurve = \"cubic\"\n[bindings]\nlook_up = \"Up\"").unwrap();
+
+		assert_eq!(map.axis_settings.dead_zone, 0.25);
+		assert_eq!(map.axis_settings.curve, ResponseCurve::Power(3.0));
+		assert_eq!(map.action(Binding::Key(VirtualKeyCode::Up)), Some(Action::LookUp));
+	}
+
+	#[test]
+	fn mouse_settings_are_read_from_their_own_section() {
+		let map = InputMap::parse("[mouse]\nsensitivity = 0.004\ninvert_y = true\nsmoothing = 0.5").unwrap();
+
+		assert_eq!(map.mouse_settings.sensitivity, 0.004);
+		assert_eq!(map.mouse_settings.invert_y, true);
+		assert_eq!(map.mouse_settings.smoothing, 0.5);
+		assert_eq!(map.mouse_settings.acceleration, 0.0);
+	}
 }
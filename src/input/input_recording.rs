@@ -0,0 +1,192 @@
+use std::collections::{VecDeque};
+use std::fs::{File};
+use std::io;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+
+use input::gamepad::{Axis};
+use input::input_event::{InputEvent};
+use input::key_names::{button_from_name, button_name, key_from_name, key_name};
+
+
+// --- RECORDING FORMAT ---
+// one event per line, prefixed with the input frame that consumed it
+//
+// 12 key W down
+// 12 button Left up
+// 12 mouse 8 -3
+// 12 axis LeftStickX -0.75
+// 12 resize 1280 720
+// 340 quit
+//
+// mouse movement is in pixels, blank lines and lines starting with # are skipped
+//
+
+pub struct InputRecorder {
+	writer: BufWriter<File>,
+}
+
+impl InputRecorder {
+	pub fn create(path: &str) -> io::Result<InputRecorder> {
+		Ok(InputRecorder { writer: BufWriter::new(try!(File::create(path))) })
+	}
+
+	// flushed every frame so a crash still leaves a usable recording
+	//
+	pub fn record(&mut self, frame: u64, events: &[InputEvent]) -> io::Result<()> {
+		for event in events {
+			try!(writeln!(self.writer, "{}", format_event(frame, event)));
+		}
+		self.writer.flush()
+	}
+}
+
+pub struct InputPlayback {
+	events: VecDeque<(u64, InputEvent)>,
+}
+
+impl InputPlayback {
+	pub fn load(path: &str) -> io::Result<InputPlayback> {
+		let reader = BufReader::new(try!(File::open(path)));
+
+		let mut events = VecDeque::new();
+		for (number, line) in reader.lines().enumerate() {
+			let line = try!(line);
+			let line = line.trim();
+
+			if line.is_empty() || line.starts_with('#') { continue }
+
+			match parse_event(line) {
+				Ok(event) => events.push_back(event),
+				Err(message) => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{}:{}: {}", path, number + 1, message))),
+			}
+		}
+		Ok(InputPlayback { events: events })
+	}
+
+	// everything recorded up to and including `frame`, apart from resizes, the recorded window
+	// isn't the one the replay is shown in so those come from live input instead
+	//
+	pub fn take(&mut self, frame: u64) -> Vec<InputEvent> {
+		let mut events = Vec::new();
+		while self.events.front().map_or(false, |&(recorded, _)| recorded <= frame) {
+			match self.events.pop_front().unwrap().1 {
+				InputEvent::Resized{ .. } => (),
+				event                     => events.push(event),
+			}
+		}
+		events
+	}
+}
+
+pub fn format_event(frame: u64, event: &InputEvent) -> String {
+	match *event {
+		InputEvent::Quit                          => format!("{} quit", frame),
+		InputEvent::KeyboardInput{ pressed, id }  => format!("{} key {} {}", frame, key_name(id), if pressed { "down" } else { "up" }),
+		InputEvent::MouseInput{ pressed, button } => format!("{} button {} {}", frame, button_name(button), if pressed { "down" } else { "up" }),
+		InputEvent::MouseMoved{ dx, dy }          => format!("{} mouse {} {}", frame, dx, dy),
+		InputEvent::AxisMoved{ axis, value }      => format!("{} axis {} {}", frame, axis.name(), value),
+		InputEvent::Resized{ width, height }      => format!("{} resize {} {}", frame, width, height),
+	}
+}
+
+pub fn parse_event(line: &str) -> Result<(u64, InputEvent), String> {
+	let words: Vec<&str> = line.split_whitespace().collect();
+
+	let frame = match words.first().and_then(|word| word.parse().ok()) {
+		Some(frame) => frame,
+		None        => return Err(format!("expected a frame number in \"{}\"", line)),
+	};
+
+	let pressed = |state: &str| match state {
+		"down" => Ok(true),
+		"up"   => Ok(false),
+		_      => Err(format!("expected down or up, found \"{}\"", state)),
+	};
+
+	let event = match (words.get(1).map(|word| *word), words.len()) {
+		(Some("quit"), 2) => InputEvent::Quit,
+		(Some("key"), 4) => {
+			let id = match key_from_name(words[2]) {
+				Some(id) => id,
+				None     => return Err(format!("unknown key \"{}\"", words[2])),
+			};
+			InputEvent::KeyboardInput{ pressed: try!(pressed(words[3])), id: id }
+		},
+		(Some("button"), 4) => {
+			let button = match button_from_name(words[2]) {
+				Some(button) => button,
+				None         => return Err(format!("unknown mouse button \"{}\"", words[2])),
+			};
+			InputEvent::MouseInput{ pressed: try!(pressed(words[3])), button: button }
+		},
+		(Some("mouse"), 4) => match (words[2].parse(), words[3].parse()) {
+			(Ok(dx), Ok(dy)) => InputEvent::MouseMoved{ dx: dx, dy: dy },
+			_                => return Err(format!("bad mouse movement \"{} {}\"", words[2], words[3])),
+		},
+		(Some("axis"), 4) => match (Axis::from_name(words[2]), words[3].parse()) {
+			(Some(axis), Ok(value)) => InputEvent::AxisMoved{ axis: axis, value: value },
+			(None, _)               => return Err(format!("unknown axis \"{}\"", words[2])),
+			(_, Err(_))             => return Err(format!("bad axis position \"{}\"", words[3])),
+		},
+		(Some("resize"), 4) => match (words[2].parse(), words[3].parse()) {
+			(Ok(width), Ok(height)) => InputEvent::Resized{ width: width, height: height },
+			_                       => return Err(format!("bad window size \"{} {}\"", words[2], words[3])),
+		},
+		_ => return Err(format!("unrecognised event \"{}\"", line)),
+	};
+	Ok((frame, event))
+}
+
+#[cfg(test)]
+mod tests {
+	use glium::glutin::{MouseButton, VirtualKeyCode};
+
+	use input::gamepad::{Axis};
+	use input::input_event::{InputEvent};
+	use super::{InputPlayback, format_event, parse_event};
+
+
+	#[test]
+	fn events_survive_a_round_trip() {
+		let events = vec![
+			(1,   InputEvent::KeyboardInput{ pressed: true,  id: VirtualKeyCode::W }),
+			(1,   InputEvent::MouseMoved{ dx: 0.1, dy: -1.0 / 3.0 }),
+			(7,   InputEvent::KeyboardInput{ pressed: false, id: VirtualKeyCode::LShift }),
+			(7,   InputEvent::MouseInput{ pressed: true, button: MouseButton::Other(4) }),
+			(9,   InputEvent::AxisMoved{ axis: Axis::RightTrigger, value: 0.625 }),
+			(9,   InputEvent::Resized{ width: 1920, height: 1080 }),
+			(340, InputEvent::Quit),
+		];
+
+		for (frame, event) in events {
+			assert_eq!(parse_event(&format_event(frame, &event)), Ok((frame, event)));
+		}
+	}
+
+	#[test]
+	fn malformed_lines_are_rejected() {
+		for line in &["", "x quit", "3 key Nope down", "3 key W sideways", "3 button Nope up", "3 mouse 1.0", "3 axis Nope 0.5", "3 axis LeftStickX far", "3 resize 1280 -720", "3 jump"] {
+			assert!(parse_event(line).is_err(), "accepted \"{}\"", line);
+		}
+	}
+
+	#[test]
+	fn playback_hands_out_events_by_frame() {
+		let mut playback = InputPlayback { events: vec![(2, InputEvent::Quit), (2, InputEvent::Quit), (5, InputEvent::Quit)].into_iter().collect() };
+
+		assert_eq!(playback.take(1).len(), 0);
+		assert_eq!(playback.take(3).len(), 2);
+		assert_eq!(playback.take(5).len(), 1);
+		assert_eq!(playback.take(6).len(), 0);
+	}
+
+	#[test]
+	fn playback_leaves_out_recorded_resizes() {
+		let mut playback = InputPlayback { events: vec![
+			(2, InputEvent::Resized{ width: 640, height: 480 }),
+			(2, InputEvent::Quit),
+		].into_iter().collect() };
+
+		assert_eq!(playback.take(2), vec![InputEvent::Quit]);
+	}
+}
@@ -0,0 +1,57 @@
+use glium::glutin::{MouseButton, VirtualKeyCode};
+
+
+// names are the variant names, so W, Space, LShift, Key1, ...
+//
+pub fn key_name(key: VirtualKeyCode) -> String {
+	format!("{:?}", key)
+}
+
+// Left, Right, Middle, then Button4, Button5, ... for the rest
+//
+pub fn button_name(button: MouseButton) -> String {
+	match button {
+		MouseButton::Other(number) => format!("Button{}", number),
+		_                          => format!("{:?}", button),
+	}
+}
+
+pub fn button_from_name(name: &str) -> Option<MouseButton> {
+	match name {
+		"Left"   => Some(MouseButton::Left),
+		"Right"  => Some(MouseButton::Right),
+		"Middle" => Some(MouseButton::Middle),
+		_ if name.starts_with("Button") => name["Button".len()..].parse().ok().map(MouseButton::Other),
+		_ => None,
+	}
+}
+
+macro_rules! key_from_name {
+	($($key:ident),*) => {
+		pub fn key_from_name(name: &str) -> Option<VirtualKeyCode> {
+			match name {
+				$(stringify!($key) => Some(VirtualKeyCode::$key),)*
+				_ => None,
+			}
+		}
+	}
+}
+
+key_from_name!(
+	Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9, Key0,
+	A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+	Escape,
+	F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12, F13, F14, F15,
+	Snapshot, Scroll, Pause,
+	Insert, Home, Delete, End, PageDown, PageUp,
+	Left, Up, Right, Down,
+	Back, Return, Space,
+	Numlock, Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
+	AbntC1, AbntC2, Add, Apostrophe, Apps, At, Ax, Backslash, Calculator, Capital, Colon, Comma, Convert,
+	Decimal, Divide, Equals, Grave, Kana, Kanji, LAlt, LBracket, LControl, LMenu, LShift, LWin, Mail,
+	MediaSelect, MediaStop, Minus, Multiply, Mute, MyComputer, NavigateForward, NavigateBackward,
+	NextTrack, NoConvert, NumpadComma, NumpadEnter, NumpadEquals, OEM102, Period, PlayPause, Power,
+	PrevTrack, RAlt, RBracket, RControl, RMenu, RShift, RWin, Semicolon, Slash, Sleep, Stop, Subtract,
+	Sysrq, Tab, Underline, Unlabeled, VolumeDown, VolumeUp, Wake, WebBack, WebFavorites, WebForward,
+	WebHome, WebRefresh, WebSearch, WebStop, Yen
+);
@@ -1,7 +0,0 @@
-#[derive(Clone, Default)]
-pub struct KeyboardState {
-	pub forward:  bool,
-	pub backward: bool,
-	pub right:    bool,
-	pub left:     bool,
-}
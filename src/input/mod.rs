@@ -1,9 +1,15 @@
-pub use self::input_frame::{InputFrame};
+pub use self::input_frame::{InputFrame, INPUT_FREQUENCY};
 pub use self::input_event::{InputEvent};
-pub use self::input_context::{InputContext};
+pub use self::input_context::{InputContext, InputSource};
+pub use self::input_map::{InputMap};
+pub use self::gamepad::{SyntheticGamepad};
 
 mod input_event;
 mod input_frame;
 mod input_map;
 mod input_context;
-mod keyboard_state;
+mod input_recording;
+mod key_names;
+mod control_state;
+mod gamepad;
+mod mouse;
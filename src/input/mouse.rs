@@ -0,0 +1,77 @@
+use cgmath::{InnerSpace, Vector2};
+
+
+// mouse movement arrives in pixels (see RenderProcessor::handle_system_events), so look speed
+// doesn't change with the window size, and is turned into view angles here
+//
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct MouseSettings {
+	// radians per pixel
+	pub sensitivity:  f32,
+	pub invert_y:     bool,
+	// 0 is off, closer to 1 averages over more frames and lags more
+	pub smoothing:    f32,
+	// extra sensitivity per pixel per frame of speed, 0 is off
+	pub acceleration: f32,
+}
+
+impl Default for MouseSettings {
+	fn default() -> MouseSettings {
+		MouseSettings {
+			sensitivity:  0.002,
+			invert_y:     false,
+			smoothing:    0.0,
+			acceleration: 0.0,
+		}
+	}
+}
+
+impl MouseSettings {
+	// this frame's movement averaged with the last frame's smoothed movement, both in pixels
+	//
+	pub fn smooth(&self, movement: Vector2<f32>, last_smoothed: Vector2<f32>) -> Vector2<f32> {
+		last_smoothed * self.smoothing + movement * (1.0 - self.smoothing)
+	}
+
+	// smoothed pixels to view angle deltas
+	//
+	pub fn look(&self, smoothed: Vector2<f32>) -> Vector2<f32> {
+		let gain = self.sensitivity * (1.0 + self.acceleration * smoothed.magnitude());
+		let y = if self.invert_y { -smoothed.y } else { smoothed.y };
+
+		Vector2::new(smoothed.x, y) * gain
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use cgmath::{Vector2};
+
+	use super::{MouseSettings};
+
+
+	#[test]
+	fn look_scales_inverts_and_accelerates() {
+		let plain    = MouseSettings { sensitivity: 0.5, .. Default::default() };
+		let inverted = MouseSettings { invert_y: true, .. plain };
+		let fast     = MouseSettings { acceleration: 0.1, .. plain };
+
+		let movement = Vector2::new(10.0, -20.0);
+
+		assert_eq!(plain.look(movement),    Vector2::new(5.0, -10.0));
+		assert_eq!(inverted.look(movement), Vector2::new(5.0,  10.0));
+		assert!(fast.look(movement).x > plain.look(movement).x);
+		assert!(fast.look(movement * 0.1).x / fast.look(movement).x < 0.1);
+	}
+
+	#[test]
+	fn smoothing_spreads_a_flick_over_frames() {
+		let settings = MouseSettings { smoothing: 0.5, .. Default::default() };
+
+		let first  = settings.smooth(Vector2::new(8.0, 0.0), Vector2::new(0.0, 0.0));
+		let second = settings.smooth(Vector2::new(0.0, 0.0), first);
+
+		assert_eq!(first,  Vector2::new(4.0, 0.0));
+		assert_eq!(second, Vector2::new(2.0, 0.0));
+	}
+}
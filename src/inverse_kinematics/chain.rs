@@ -1,14 +1,19 @@
-use cgmath::{Matrix4, Rad, SquareMatrix, Vector3};
+use std::f32::{INFINITY, NEG_INFINITY};
+
+use cgmath::{InnerSpace, Matrix4, One, Quaternion, Rad, SquareMatrix, Vector3, Vector4};
 use glium::{IndexBuffer, VertexBuffer};
 use glium::backend::{Facade};
 use glium::index::{PrimitiveType};
 
-use inverse_kinematics::{State};
+use inverse_kinematics::{Easing, Solver, State};
 use model::{Model};
 use render::vertices::{ForwardVertex};
 
 
-#[allow(dead_code)]
+// bones are drawn as boxes this far out from their centre line
+//
+pub const BONE_HALF_WIDTH: f32 = 0.4;
+
 #[derive(Copy, Clone)]
 pub enum Axis {
 	X = 0,
@@ -26,30 +31,137 @@ impl Axis {
 	}
 }
 
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum JointKind {
+	Revolute,  // its angle spins it about axis
+	Prismatic, // its "angle" is a displacement along axis
+}
+
+// a joint turns into its rest orientation, moves about/along its axis (given in that rest frame)
+// and then steps over its offset to where the next joint hangs, its bone is drawn along that offset
+//
 #[derive(Copy, Clone)]
 pub struct Joint {
-	pub length: f32,
-	pub axis:   Axis,
+	pub kind:      JointKind,
+	pub axis:      Vector3<f32>,
+	pub offset:    Vector3<f32>,
+	pub rest:      Quaternion<f32>,
+	pub min_angle: f32,
+	pub max_angle: f32,
+
+	// per frame, only the updater's Seeking state respects these, solvers jump straight to their answer
+	pub max_velocity:     f32,
+	pub max_acceleration: f32,
 }
 
+impl Joint {
+	pub fn revolute(axis: Vector3<f32>, length: f32) -> Joint {
+		Joint {
+			kind:      JointKind::Revolute,
+			axis:      axis.normalize(),
+			offset:    Vector3::new(0.0, length, 0.0),
+			rest:      Quaternion::one(),
+			min_angle: NEG_INFINITY,
+			max_angle: INFINITY,
+
+			max_velocity:     INFINITY,
+			max_acceleration: INFINITY,
+		}
+	}
+
+	#[cfg(test)]
+	pub fn prismatic(axis: Vector3<f32>, offset: Vector3<f32>) -> Joint {
+		Joint {
+			kind:      JointKind::Prismatic,
+			axis:      axis.normalize(),
+			offset:    offset,
+			rest:      Quaternion::one(),
+			min_angle: 0.0,
+			max_angle: 0.0,
+
+			max_velocity:     INFINITY,
+			max_acceleration: INFINITY,
+		}
+	}
+
+	pub fn with_limits(self, min_angle: f32, max_angle: f32) -> Joint {
+		Joint { min_angle: min_angle, max_angle: max_angle, .. self }
+	}
+
+	pub fn with_velocity_limits(self, max_velocity: f32, max_acceleration: f32) -> Joint {
+		Joint { max_velocity: max_velocity, max_acceleration: max_acceleration, .. self }
+	}
+
+	#[cfg(test)]
+	pub fn with_offset(self, offset: Vector3<f32>) -> Joint {
+		Joint { offset: offset, .. self }
+	}
+
+	pub fn length(&self) -> f32 {
+		self.offset.magnitude()
+	}
+
+	// furthest this joint can carry the next one away from its pivot
+	//
+	pub fn reach(&self) -> f32 {
+		match self.kind {
+			JointKind::Revolute  => self.length(),
+			JointKind::Prismatic => self.length() + self.min_angle.abs().max(self.max_angle.abs()),
+		}
+	}
+
+	pub fn clamp(&self, angle: f32) -> f32 {
+		angle.max(self.min_angle).min(self.max_angle)
+	}
+
+	// rest orientation and motion but not the offset, i.e. the frame the bone is drawn in
+	//
+	pub fn motion(&self, angle: f32) -> Matrix4<f32> {
+		let rest = Matrix4::from(self.rest);
+
+		match self.kind {
+			JointKind::Revolute  => rest * Matrix4::from_axis_angle(self.axis, Rad(angle)),
+			JointKind::Prismatic => rest * Matrix4::from_translation(self.axis * angle),
+		}
+	}
+
+	pub fn transform(&self, angle: f32) -> Matrix4<f32> {
+		self.motion(angle) * Matrix4::from_translation(self.offset)
+	}
+
+	// bone models run along +Y so they get turned onto the offset
+	//
+	pub fn bone_transform(&self, angle: f32) -> Matrix4<f32> {
+		let alignment = Quaternion::from_arc(Vector3::unit_y(), self.offset, Some(Vector3::unit_x()));
+
+		self.motion(angle) * Matrix4::from(alignment)
+	}
+
+	pub fn world_axis(&self, parent: &Matrix4<f32>) -> Vector3<f32> {
+		(parent * Matrix4::from(self.rest) * self.axis.extend(0.0)).truncate()
+	}
+
+	// (linear, angular) velocity of `point` per unit of this joint's angle, `parent` is the transform the joint hangs off
+	//
+	pub fn jacobian_column(&self, parent: &Matrix4<f32>, point: Vector3<f32>) -> (Vector3<f32>, Vector3<f32>) {
+		let pivot = (parent * Vector4::unit_w()).truncate();
+		let axis  = self.world_axis(parent);
+
+		match self.kind {
+			JointKind::Revolute  => (axis.cross(point - pivot), axis),
+			JointKind::Prismatic => (axis, Vector3::new(0.0, 0.0, 0.0)),
+		}
+	}
+}
+
+#[derive(Clone)]
 pub struct Chain {
 	pub joints:   Vec<Joint>,
 	pub angles:   Vec<f32>,
 	pub state:    State,
 	pub position: Vector3<f32>,
-	pub ik_fun:   fn(&Chain, Vector3<f32>) -> Vec<f32>,
-}
-
-impl Clone for Chain {
-	fn clone(&self) -> Self {
-		Self {
-			joints: self.joints.clone(),
-			angles: self.angles.clone(),
-			state: self.state.clone(),
-			position: self.position,
-			ik_fun: self.ik_fun
-		}
-	}
+	pub solver:   Solver,
+	pub easing:   Easing,
 }
 
 impl Chain {
@@ -58,12 +170,8 @@ impl Chain {
 		let mut parent: Matrix4<f32> = Matrix4::identity();
 
 		for (joint, angle) in self.joints.iter().zip(self.angles.iter()) {
-			let r = Matrix4::from_axis_angle(joint.axis.to_vector3(), Rad(*angle));
-			let t = Matrix4::from_translation(Vector3::new(0.0, joint.length, 0.0));
-
-			let pr = parent * r;
-			if joint.length != 0.0 { models.push(pr) }
-			parent = pr * t;
+			if joint.length() != 0.0 { models.push(parent * joint.bone_transform(*angle)) }
+			parent = parent * joint.transform(*angle);
 		}
 		models
 	}
@@ -77,28 +185,40 @@ impl Chain {
 		let mut accumulator: Matrix4<f32> = Matrix4::identity();
 
 		for (joint, angle) in self.joints.iter().zip(angles.iter()) {
-			let r = Matrix4::from_axis_angle(joint.axis.to_vector3(), Rad(*angle));
-			let t = Matrix4::from_translation(Vector3::new(0.0, joint.length, 0.0));
-
-			accumulator = accumulator * r * t;
+			accumulator = accumulator * joint.transform(*angle);
 			models.push(accumulator);
 		}
 		models
 	}
 
+	pub fn end_effector_with_angles(&self, angles: &[f32]) -> Vector3<f32> {
+		(self.end_effector_transform_with_angles(angles) * Vector4::unit_w()).truncate()
+	}
+
+	pub fn end_effector_transform_with_angles(&self, angles: &[f32]) -> Matrix4<f32> {
+		match self.cumulative_transforms_with_angles(angles).last() {
+			Some(transform) => *transform,
+			None            => Matrix4::identity(),
+		}
+	}
+
+	pub fn reach(&self) -> f32 {
+		self.joints.iter().map(|joint| joint.reach()).sum()
+	}
+
 	pub fn model<F: Facade>(&self, facade: &F) -> Model {
-		const S: f32 = 0.4f32;
+		const S: f32 = BONE_HALF_WIDTH;
 		const NUM_FACES: usize = 6;
 		const NUM_VERTS_PER_FACE: usize = 4;
 		const NUM_INDICES_PER_FACE: usize = 6;
 
-		let num_visible_joints = self.joints.iter().filter(|j| j.length != 0.0).count();
+		let num_visible_joints = self.joints.iter().filter(|j| j.length() != 0.0).count();
 
 		let mut vertices = Vec::with_capacity(num_visible_joints * NUM_FACES * NUM_VERTS_PER_FACE);
 		let mut indices  = Vec::with_capacity(num_visible_joints * NUM_FACES * NUM_INDICES_PER_FACE);
 
-		for joint in self.joints.iter().filter(|j| j.length != 0.0) {
-			let len = joint.length;
+		for joint in self.joints.iter().filter(|j| j.length() != 0.0) {
+			let len = joint.length();
 			let base = vertices.len();
 
 			vertices.push(ForwardVertex { position: [-S, 0f32,  S], normal: [ 0f32,  0f32,  1f32] });
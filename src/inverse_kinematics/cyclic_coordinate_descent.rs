@@ -1,14 +1,33 @@
-use cgmath::{InnerSpace, Matrix4, Rad, SquareMatrix, Vector3, Vector4};
+use cgmath::{InnerSpace, Matrix4, SquareMatrix, Vector4};
 
-use inverse_kinematics::{Chain};
+use inverse_kinematics::{Chain, JointKind, SolveResult, Target};
 
 
-pub fn cyclic_coordinate_descent(chain: &Chain, target: Vector3<f32>) -> Vec<f32> {
-	const DISTANCE_THRESHOLD: f32 = 0.01; // ADD DYNOMISM
-	const PERP_LENGTH_THRESHOLD: f32 = 0.0001;
-	const MAX_ITERATIONS: usize = 60;
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct CyclicCoordinateDescentParameters {
+	pub distance_threshold:    f32,
+	pub orientation_threshold: f32,
+	pub orientation_weight:    f32, // 0 only chases position, 1 only chases orientation
+	pub perp_length_threshold: f32,
+	pub max_iterations:        usize,
+}
 
-	if chain.joints.is_empty() { return Vec::new() }
+impl Default for CyclicCoordinateDescentParameters {
+	fn default() -> CyclicCoordinateDescentParameters {
+		CyclicCoordinateDescentParameters {
+			distance_threshold:    0.01,
+			orientation_threshold: 0.01,
+			orientation_weight:    0.5,
+			perp_length_threshold: 0.0001,
+			max_iterations:        60,
+		}
+	}
+}
+
+pub fn cyclic_coordinate_descent(chain: &Chain, target: Target, parameters: &CyclicCoordinateDescentParameters) -> SolveResult {
+	if chain.joints.is_empty() {
+		return SolveResult::new(chain, target, Vec::new(), 0, parameters.distance_threshold, parameters.orientation_threshold, false)
+	}
 
 	let len = chain.joints.len();
 
@@ -16,10 +35,17 @@ pub fn cyclic_coordinate_descent(chain: &Chain, target: Vector3<f32>) -> Vec<f32
 
 	let mut angles: Vec<f32> = chain.angles.to_vec();
 
-	let mut end = (cumulative_transforms.last().unwrap() * Vector4::unit_w()).truncate();
+	let mut end_transform = *cumulative_transforms.last().unwrap();
+
+	let mut iterations = 0;
+
+	for _ in 0..parameters.max_iterations {
+		let end = (end_transform * Vector4::unit_w()).truncate();
+
+		if (end - target.position).magnitude() < parameters.distance_threshold
+		&& target.orientation_error(&end_transform).magnitude() < parameters.orientation_threshold { break }
 
-	for _ in 0..MAX_ITERATIONS {
-		if (end - target).magnitude() < DISTANCE_THRESHOLD { break }
+		iterations += 1;
 
 		let mut reverse_accumulator: Matrix4<f32> = Matrix4::identity();
 
@@ -34,42 +60,54 @@ pub fn cyclic_coordinate_descent(chain: &Chain, target: Vector3<f32>) -> Vec<f32
 				)
 			};
 			let root = (prev_transform * Vector4::unit_w()).truncate();
-			let axis = (prev_transform * joint.axis.to_vector3().extend(0.0)).truncate();
+			let axis = joint.world_axis(&prev_transform);
+			let end  = (end_transform  * Vector4::unit_w()).truncate();
 
-			let target_dir = target - root;
-			let target_perp = target_dir - (axis * axis.dot(target_dir));
-			let target_perp_len = target_perp.magnitude();
-
-			if target_perp_len < PERP_LENGTH_THRESHOLD { continue }
-
-			let target_perp_norm = target_perp / target_perp_len;
-
-			let end_dir = end - root;
-			let end_perp = end_dir - (axis * axis.dot(end_dir));
-			let end_perp_len = end_perp.magnitude();
-
-			if end_perp_len < PERP_LENGTH_THRESHOLD { continue }
+			// prismatic joints just slide the end along their axis as far towards the target as it goes
+			//
+			let position_delta = if joint.kind == JointKind::Prismatic {
+				axis.dot(target.position - end)
+			} else {
+				let target_dir = target.position - root;
+				let target_perp = target_dir - (axis * axis.dot(target_dir));
+				let target_perp_len = target_perp.magnitude();
+
+				let end_dir = end - root;
+				let end_perp = end_dir - (axis * axis.dot(end_dir));
+				let end_perp_len = end_perp.magnitude();
+
+				if target_perp_len < parameters.perp_length_threshold || end_perp_len < parameters.perp_length_threshold {
+					0.0
+				} else {
+					let cross = (end_perp / end_perp_len).cross(target_perp / target_perp_len);
+					let magnitude = cross.magnitude();
+					let sign = if cross.dot(axis) > 0.0 { 1.0 } else { -1.0 };
+					sign * magnitude.asin()
+				}
+			};
 
-			let end_perp_norm = end_perp / end_perp_len;
+			// the part of the orientation error we can fix by spinning about this joint's axis
+			//
+			let delta = match target.orientation {
+				Some(_) if joint.kind == JointKind::Revolute => {
+					let orientation_delta = target.orientation_error(&end_transform).dot(axis);
+					let weight = parameters.orientation_weight;
+
+					(1.0 - weight) * position_delta + weight * orientation_delta
+				},
+				_ => position_delta,
+			};
 
-			let cross = end_perp_norm.cross(target_perp_norm);
-			let magnitude = cross.magnitude();
-			let sign = if cross.dot(axis) > 0.0 { 1.0 } else { -1.0 };
-			*angle += sign * magnitude.asin();
+			*angle = joint.clamp(*angle + delta);
 
-			// TODO: this assumption about Y being our offset direction needs to be put in a single place
-			//
-			let translation = Matrix4::from_translation(Vector3::new(0.0, joint.length, 0.0));
-			let rotation    = Matrix4::from_axis_angle(joint.axis.to_vector3(), Rad(*angle));
-			let transform   = rotation * translation;
+			let transform = joint.transform(*angle);
 
 			*current_transform = prev_transform * transform;
 			reverse_accumulator = transform * reverse_accumulator;
 
-			let total = prev_transform * reverse_accumulator;
-			end = (total * Vector4::unit_w()).truncate();
+			end_transform = prev_transform * reverse_accumulator;
 		}
 	}
 
-	angles
+	SolveResult::new(chain, target, angles, iterations, parameters.distance_threshold, parameters.orientation_threshold, false)
 }
@@ -0,0 +1,180 @@
+use cgmath::{Vector4, InnerSpace, Matrix4, SquareMatrix};
+use nalgebra::{DMatrix, DVector};
+
+use inverse_kinematics::{Chain, Skeleton, SolveResult, Target};
+
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct DampedLeastSquaresParameters {
+	pub distance_threshold:    f32,
+	pub orientation_threshold: f32,
+	pub max_iterations:        usize,
+	pub initial_damping:       f32,
+	pub min_damping:           f32,
+	pub max_damping:           f32,
+}
+
+impl Default for DampedLeastSquaresParameters {
+	fn default() -> DampedLeastSquaresParameters {
+		DampedLeastSquaresParameters {
+			distance_threshold:    0.01,
+			orientation_threshold: 0.01,
+			max_iterations:        60,
+			initial_damping:       1.0,
+			min_damping:           0.001,
+			max_damping:           1000.0,
+		}
+	}
+}
+
+// Levenberg-Marquardt style damping: shrink the damping factor while steps reduce the error
+// and grow it (rejecting the step) when they don't, so near singularities we fall back to
+// small, well conditioned steps instead of blowing up
+//
+pub fn damped_least_squares(chain: &Chain, target: Target, parameters: &DampedLeastSquaresParameters) -> SolveResult {
+	if chain.joints.is_empty() {
+		return SolveResult::new(chain, target, Vec::new(), 0, parameters.distance_threshold, parameters.orientation_threshold, false)
+	}
+
+	let rows = if target.orientation.is_some() { 6 } else { 3 };
+
+	let mut angles = chain.angles.clone();
+	let mut damping = parameters.initial_damping;
+	let mut error = pose_error(chain, target, &angles);
+	let mut iterations = 0;
+
+	for _ in 0..parameters.max_iterations {
+		if error.0 < parameters.distance_threshold && error.1 < parameters.orientation_threshold { break }
+
+		iterations += 1;
+
+		let mut cumulative_transforms = vec![Matrix4::identity()];
+		cumulative_transforms.extend(chain.cumulative_transforms_with_angles(&angles).into_iter());
+
+		let end_transform = *cumulative_transforms.last().unwrap();
+		let end = (end_transform * Vector4::unit_w()).truncate();
+
+		let mut jacobian = DMatrix::from_element(rows, chain.joints.len(), 0f32);
+
+		for (idx, (transform, joint)) in cumulative_transforms.iter().zip(chain.joints.iter()).enumerate() {
+			let (j, a) = joint.jacobian_column(transform, end);
+			unsafe {
+				*jacobian.get_unchecked_mut(0, idx) = j.x;
+				*jacobian.get_unchecked_mut(1, idx) = j.y;
+				*jacobian.get_unchecked_mut(2, idx) = j.z;
+				if rows == 6 {
+					*jacobian.get_unchecked_mut(3, idx) = a.x;
+					*jacobian.get_unchecked_mut(4, idx) = a.y;
+					*jacobian.get_unchecked_mut(5, idx) = a.z;
+				}
+			}
+		}
+		let desired_change      = target.position - end;
+		let desired_orientation = target.orientation_error(&end_transform);
+
+		let desired = [
+			desired_change.x,      desired_change.y,      desired_change.z,
+			desired_orientation.x, desired_orientation.y, desired_orientation.z,
+		];
+		let dc: DVector<f32> = DVector::from_column_slice(rows, &desired[..rows]);
+
+		let transpose = jacobian.transpose();
+
+		let damped = jacobian * transpose.clone() + DMatrix::from_diagonal_element(rows, rows, damping * damping);
+
+		let inverse = match damped.try_inverse() {
+			Some(inverse) => inverse,
+			None => {
+				damping = (damping * 2.0).min(parameters.max_damping);
+				continue;
+			},
+		};
+
+		let delta: DVector<f32> = transpose * inverse * dc;
+
+		let candidate: Vec<f32> = angles.iter().zip(delta.iter()).zip(chain.joints.iter()).map(
+			|((angle, delta), joint)| joint.clamp(angle + delta)
+		).collect();
+
+		let candidate_error = pose_error(chain, target, &candidate);
+
+		if magnitude(candidate_error) < magnitude(error) {
+			angles = candidate;
+			error = candidate_error;
+			damping = (damping * 0.5).max(parameters.min_damping);
+		} else {
+			damping = (damping * 2.0).min(parameters.max_damping);
+		}
+	}
+	// damping pinned at its ceiling means every step was rejected, we're stuck at a singularity
+	//
+	SolveResult::new(chain, target, angles, iterations, parameters.distance_threshold, parameters.orientation_threshold, damping >= parameters.max_damping)
+}
+
+pub fn damped_least_squares_skeleton(skeleton: &Skeleton, parameters: &DampedLeastSquaresParameters) -> SolveResult {
+	let mut angles = skeleton.angles.clone();
+	let mut damping = parameters.initial_damping;
+	let mut error = weighted_error(skeleton, &angles);
+	let mut iterations = 0;
+
+	for _ in 0..parameters.max_iterations {
+		if skeleton.residuals_with_angles(&angles).iter().all(
+			|&(position, orientation)| position < parameters.distance_threshold && orientation < parameters.orientation_threshold
+		) { break }
+
+		iterations += 1;
+
+		let (jacobian, desired) = skeleton.stacked_jacobian_with_angles(&angles);
+
+		let rows = jacobian.nrows();
+		let transpose = jacobian.transpose();
+
+		let damped = jacobian * transpose.clone() + DMatrix::from_diagonal_element(rows, rows, damping * damping);
+
+		let inverse = match damped.try_inverse() {
+			Some(inverse) => inverse,
+			None => {
+				damping = (damping * 2.0).min(parameters.max_damping);
+				continue;
+			},
+		};
+
+		let delta: DVector<f32> = transpose * inverse * desired;
+
+		let candidate: Vec<f32> = angles.iter().zip(delta.iter()).zip(skeleton.bones.iter()).map(
+			|((angle, delta), bone)| bone.joint.clamp(angle + delta)
+		).collect();
+
+		let candidate_error = weighted_error(skeleton, &candidate);
+
+		if candidate_error < error {
+			angles = candidate;
+			error = candidate_error;
+			damping = (damping * 0.5).max(parameters.min_damping);
+		} else {
+			damping = (damping * 2.0).min(parameters.max_damping);
+		}
+	}
+	SolveResult::from_skeleton(skeleton, angles, iterations, parameters.distance_threshold, parameters.orientation_threshold, damping >= parameters.max_damping)
+}
+
+// (position, orientation) residuals
+//
+fn pose_error(chain: &Chain, target: Target, angles: &[f32]) -> (f32, f32) {
+	let transform = chain.end_effector_transform_with_angles(angles);
+
+	(
+		((transform * Vector4::unit_w()).truncate() - target.position).magnitude(),
+		target.orientation_error(&transform).magnitude(),
+	)
+}
+
+fn magnitude(error: (f32, f32)) -> f32 {
+	(error.0 * error.0 + error.1 * error.1).sqrt()
+}
+
+fn weighted_error(skeleton: &Skeleton, angles: &[f32]) -> f32 {
+	skeleton.residuals_with_angles(angles).iter().zip(skeleton.effectors.iter()).map(
+		|(&error, effector)| effector.weight * magnitude(error)
+	).sum()
+}
@@ -0,0 +1,44 @@
+use std::f32::consts::{PI};
+
+
+// how far along (0 to 1) a Seeking transition should be at a given fraction (0 to 1) of its frames
+//
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Easing {
+	Linear,
+	EaseInOut,
+	// tangents are in whole transitions per transition, both zero gives smoothstep
+	CubicHermite { start_tangent: f32, end_tangent: f32 },
+	// omega is how many time constants fit in the transition, higher settles sooner
+	CriticallyDampedSpring { omega: f32 },
+}
+
+impl Default for Easing {
+	fn default() -> Easing {
+		Easing::Linear
+	}
+}
+
+impl Easing {
+	pub fn ease(&self, t: f32) -> f32 {
+		let t = t.max(0.0).min(1.0);
+
+		match *self {
+			Easing::Linear => t,
+			Easing::EaseInOut => 0.5 - 0.5 * (PI * t).cos(),
+			Easing::CubicHermite { start_tangent, end_tangent } => {
+				let t2 = t * t;
+				let t3 = t2 * t;
+
+				(t3 - 2.0 * t2 + t) * start_tangent + (-2.0 * t3 + 3.0 * t2) + (t3 - t2) * end_tangent
+			},
+			Easing::CriticallyDampedSpring { omega } => {
+				// the spring never quite arrives, so scale it to land exactly on the target at t = 1
+				//
+				let spring = |t: f32| 1.0 - (1.0 + omega * t) * (-omega * t).exp();
+
+				spring(t) / spring(1.0)
+			},
+		}
+	}
+}
@@ -0,0 +1,131 @@
+use cgmath::{InnerSpace, Matrix3, Matrix4, SquareMatrix, Vector3, Vector4};
+
+use inverse_kinematics::{Chain, JointKind, SolveResult, Target};
+
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct ForwardAndBackwardReachingParameters {
+	pub distance_threshold:    f32,
+	pub orientation_threshold: f32,
+	pub max_iterations:        usize,
+}
+
+impl Default for ForwardAndBackwardReachingParameters {
+	fn default() -> ForwardAndBackwardReachingParameters {
+		ForwardAndBackwardReachingParameters {
+			distance_threshold:    0.01,
+			orientation_threshold: 0.01,
+			max_iterations:        20,
+		}
+	}
+}
+
+// FABRIK: each iteration does one forward and one backward reaching pass over the joint positions
+// and then converts the positions back into angles about each joint's axis (respecting its limits)
+// an orientation target only pins the direction of the last bone, twist about it is left free
+//
+pub fn forward_and_backward_reaching(chain: &Chain, target: Target, parameters: &ForwardAndBackwardReachingParameters) -> SolveResult {
+	if chain.joints.is_empty() {
+		return SolveResult::new(chain, target, Vec::new(), 0, parameters.distance_threshold, parameters.orientation_threshold, false)
+	}
+
+	let mut angles = chain.angles.clone();
+	let mut iterations = 0;
+
+	for _ in 0..parameters.max_iterations {
+		let cumulative_transforms = chain.cumulative_transforms_with_angles(&angles);
+
+		let mut positions = vec![Vector3::new(0f32, 0f32, 0f32)];
+		positions.extend(cumulative_transforms.iter().map(
+			|transform| (transform * Vector4::unit_w()).truncate()
+		));
+
+		let end_transform = cumulative_transforms.last().unwrap();
+
+		if (positions.last().unwrap() - target.position).magnitude() < parameters.distance_threshold
+		&& target.orientation_error(end_transform).magnitude()      < parameters.orientation_threshold { break }
+
+		iterations += 1;
+
+		let root = positions[0];
+		let len = chain.joints.len();
+
+		// measured from the current pose rather than the joints so prismatic joints keep their present extension
+		//
+		let lengths: Vec<f32> = positions.windows(2).map(|pair| (pair[1] - pair[0]).magnitude()).collect();
+
+		positions[len] = target.position;
+
+		let mut first = len;
+		if let Some(orientation) = target.orientation {
+			let last = chain.joints[len - 1];
+			if last.length() != 0.0 {
+				positions[len - 1] = target.position - Matrix3::from(orientation) * last.offset;
+				first = len - 1;
+			}
+		}
+
+		for i in (0..first).rev() {
+			positions[i] = reach(positions[i + 1], positions[i], lengths[i]);
+		}
+
+		positions[0] = root;
+		for i in 0..len {
+			positions[i + 1] = reach(positions[i], positions[i + 1], lengths[i]);
+		}
+
+		angles = positions_to_angles(chain, &angles, &positions);
+	}
+	SolveResult::new(chain, target, angles, iterations, parameters.distance_threshold, parameters.orientation_threshold, false)
+}
+
+fn reach(anchor: Vector3<f32>, toward: Vector3<f32>, length: f32) -> Vector3<f32> {
+	const EPSILON: f32 = 0.0001;
+
+	let direction = toward - anchor;
+	let magnitude = direction.magnitude();
+
+	if magnitude < EPSILON { toward }
+	else                   { anchor + direction * (length / magnitude) }
+}
+
+// rotates each joint in turn (root first) so the end of the next visible bone points at its desired position,
+// prismatic joints instead slide that end towards it
+//
+fn positions_to_angles(chain: &Chain, angles: &[f32], positions: &[Vector3<f32>]) -> Vec<f32> {
+	const PERP_LENGTH_THRESHOLD: f32 = 0.0001;
+
+	let len = chain.joints.len();
+	let mut angles = angles.to_vec();
+
+	for i in 0..len {
+		let bone = match (i..len).find(|&j| chain.joints[j].length() != 0.0) {
+			Some(bone) => bone,
+			None       => break,
+		};
+
+		let transforms = chain.cumulative_transforms_with_angles(&angles);
+		let parent = if i > 0 { transforms[i - 1] } else { Matrix4::identity() };
+
+		let root = (parent * Vector4::unit_w()).truncate();
+		let axis = chain.joints[i].world_axis(&parent);
+
+		let current = (transforms[bone] * Vector4::unit_w()).truncate() - root;
+		let desired = positions[bone + 1] - root;
+
+		if chain.joints[i].kind == JointKind::Prismatic {
+			angles[i] = chain.joints[i].clamp(angles[i] + axis.dot(desired - current));
+			continue
+		}
+
+		let current_perp = current - axis * axis.dot(current);
+		let desired_perp = desired - axis * axis.dot(desired);
+
+		if current_perp.magnitude() < PERP_LENGTH_THRESHOLD || desired_perp.magnitude() < PERP_LENGTH_THRESHOLD { continue }
+
+		let delta = axis.dot(current_perp.cross(desired_perp)).atan2(current_perp.dot(desired_perp));
+
+		angles[i] = chain.joints[i].clamp(angles[i] + delta);
+	}
+	angles
+}
@@ -1,53 +1,138 @@
-use cgmath::{Vector3, Vector4, InnerSpace, Matrix4, SquareMatrix};
-use nalgebra::{Matrix, MatrixVec, Dynamic, U3, DVector};
-use nalgebra::Vector3 as NALGVector3;
+use cgmath::{Vector4, InnerSpace, Matrix4, SquareMatrix};
+use nalgebra::{DMatrix, DVector};
 
-use inverse_kinematics::{Chain};
+use inverse_kinematics::{Chain, Skeleton, SolveResult, Target};
 
 
-type JMatrix = Matrix<f32, U3, Dynamic, MatrixVec<f32, U3, Dynamic>>;
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct JacobianPseudoInverseParameters {
+	pub distance_threshold:    f32,
+	pub orientation_threshold: f32,
+	pub max_iterations:        usize,
+}
+
+impl Default for JacobianPseudoInverseParameters {
+	fn default() -> JacobianPseudoInverseParameters {
+		JacobianPseudoInverseParameters {
+			distance_threshold:    0.01,
+			orientation_threshold: 0.01,
+			max_iterations:        60,
+		}
+	}
+}
 
-pub fn jacobian_pseudo_inverse(chain: &Chain, target: Vector3<f32>) -> Vec<f32> {
-	const DISTANCE_THRESHOLD: f32 = 0.01; // ADD DYNOMISM
-	const MAX_ITERATIONS: usize = 60;
+// 3xN jacobian for position only targets, 6xN (position rows then orientation rows) otherwise
+//
+pub fn jacobian_pseudo_inverse(chain: &Chain, target: Target, parameters: &JacobianPseudoInverseParameters) -> SolveResult {
+	if chain.joints.is_empty() {
+		return SolveResult::new(chain, target, Vec::new(), 0, parameters.distance_threshold, parameters.orientation_threshold, false)
+	}
 
-	if chain.joints.is_empty() { return Vec::new() }
+	let rows = if target.orientation.is_some() { 6 } else { 3 };
 
 	let mut angles: DVector<f32> = DVector::from_column_slice(chain.angles.len(), chain.angles.as_slice());
 
-	for _ in 0..MAX_ITERATIONS {
+	let mut iterations = 0;
+	let mut singular = false;
+
+	for _ in 0..parameters.max_iterations {
 		let mut cumulative_transforms = vec![Matrix4::identity()];
 		cumulative_transforms.extend(chain.cumulative_transforms_with_angles(angles.as_slice()).into_iter());
 
-		let mut jacobian = JMatrix::from_element(chain.joints.len(), 0f32);
+		let mut jacobian = DMatrix::from_element(rows, chain.joints.len(), 0f32);
 
-		let end = (cumulative_transforms.last().unwrap() * Vector4::unit_w()).truncate();
+		let end_transform = *cumulative_transforms.last().unwrap();
+		let end = (end_transform * Vector4::unit_w()).truncate();
 
-		if (end - target).magnitude() < DISTANCE_THRESHOLD { break }
+		let desired_change      = target.position - end;
+		let desired_orientation = target.orientation_error(&end_transform);
 
-		for (idx, (transform, joint)) in cumulative_transforms.iter().zip(chain.joints.iter()).enumerate() {
-			let position = (transform * Vector4::unit_w()).truncate();
+		if desired_change.magnitude()      < parameters.distance_threshold
+		&& desired_orientation.magnitude() < parameters.orientation_threshold { break }
 
-			let axis = (transform * joint.axis.to_vector3().extend(0f32)).truncate();
+		iterations += 1;
 
-			let j = axis.cross(target - position);
+		for (idx, (transform, joint)) in cumulative_transforms.iter().zip(chain.joints.iter()).enumerate() {
+			let (j, a) = joint.jacobian_column(transform, end);
 			unsafe {
 				*jacobian.get_unchecked_mut(0, idx) = j.x;
 				*jacobian.get_unchecked_mut(1, idx) = j.y;
 				*jacobian.get_unchecked_mut(2, idx) = j.z;
+				if rows == 6 {
+					*jacobian.get_unchecked_mut(3, idx) = a.x;
+					*jacobian.get_unchecked_mut(4, idx) = a.y;
+					*jacobian.get_unchecked_mut(5, idx) = a.z;
+				}
 			}
 		}
-		let desired_change = target - end;
 
-		let dc: NALGVector3<f32> = NALGVector3::new(desired_change.x, desired_change.y, desired_change.z);
+		let error = [
+			desired_change.x,      desired_change.y,      desired_change.z,
+			desired_orientation.x, desired_orientation.y, desired_orientation.z,
+		];
+		let dc: DVector<f32> = DVector::from_column_slice(rows, &error[..rows]);
 
 		let transpose = jacobian.transpose();
 
-		let inverse = (jacobian * transpose.clone()).try_inverse().unwrap();
-
-		let pseudo_inverse = transpose * inverse;
+		// right inverse when the chain has at least as many joints as constraints, left inverse otherwise
+		// singular configurations have no inverse, damped_least_squares handles these gracefully
+		//
+		let pseudo_inverse = if rows <= chain.joints.len() {
+			match (jacobian * transpose.clone()).try_inverse() {
+				Some(inverse) => transpose * inverse,
+				None          => { singular = true; break },
+			}
+		} else {
+			match (transpose.clone() * jacobian).try_inverse() {
+				Some(inverse) => inverse * transpose,
+				None          => { singular = true; break },
+			}
+		};
 
 		angles += pseudo_inverse * dc;
+
+		for (angle, joint) in angles.iter_mut().zip(chain.joints.iter()) {
+			*angle = joint.clamp(*angle);
+		}
+	}
+	SolveResult::new(chain, target, Vec::from(angles.as_slice()), iterations, parameters.distance_threshold, parameters.orientation_threshold, singular)
+}
+
+pub fn jacobian_pseudo_inverse_skeleton(skeleton: &Skeleton, parameters: &JacobianPseudoInverseParameters) -> SolveResult {
+	let mut angles = skeleton.angles.clone();
+
+	let mut iterations = 0;
+	let mut singular = false;
+
+	for _ in 0..parameters.max_iterations {
+		if skeleton.residuals_with_angles(&angles).iter().all(
+			|&(position, orientation)| position < parameters.distance_threshold && orientation < parameters.orientation_threshold
+		) { break }
+
+		iterations += 1;
+
+		let (jacobian, error) = skeleton.stacked_jacobian_with_angles(&angles);
+
+		let rows = jacobian.nrows();
+		let transpose = jacobian.transpose();
+
+		let pseudo_inverse = if rows <= skeleton.bones.len() {
+			match (jacobian * transpose.clone()).try_inverse() {
+				Some(inverse) => transpose * inverse,
+				None          => { singular = true; break },
+			}
+		} else {
+			match (transpose.clone() * jacobian).try_inverse() {
+				Some(inverse) => inverse * transpose,
+				None          => { singular = true; break },
+			}
+		};
+
+		let delta = pseudo_inverse * error;
+
+		for ((angle, delta), bone) in angles.iter_mut().zip(delta.iter()).zip(skeleton.bones.iter()) {
+			*angle = bone.joint.clamp(*angle + delta);
+		}
 	}
-	Vec::from(angles.as_slice())
+	SolveResult::from_skeleton(skeleton, angles, iterations, parameters.distance_threshold, parameters.orientation_threshold, singular)
 }
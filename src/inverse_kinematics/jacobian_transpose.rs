@@ -1,36 +1,81 @@
-use cgmath::{Vector3, Vector4, InnerSpace, Matrix4, SquareMatrix};
+use cgmath::{Vector4, InnerSpace, Matrix4, SquareMatrix};
 
-use inverse_kinematics::{Chain};
+use inverse_kinematics::{Chain, Skeleton, SolveResult, Target};
 
 
-pub fn jacobian_transpose(chain: &Chain, target: Vector3<f32>) -> Vec<f32> {
-	const DISTANCE_THRESHOLD: f32 = 0.01; // ADD DYNOMISM
-	const MAX_ITERATIONS: usize = 60;
-	const FUDGE: f32 = 0.01;
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct JacobianTransposeParameters {
+	pub distance_threshold:    f32,
+	pub orientation_threshold: f32,
+	pub max_iterations:        usize,
+	pub step_size:             f32,
+}
+
+impl Default for JacobianTransposeParameters {
+	fn default() -> JacobianTransposeParameters {
+		JacobianTransposeParameters {
+			distance_threshold:    0.01,
+			orientation_threshold: 0.01,
+			max_iterations:        60,
+			step_size:             0.01,
+		}
+	}
+}
 
-	if chain.joints.is_empty() { return Vec::new() }
+pub fn jacobian_transpose(chain: &Chain, target: Target, parameters: &JacobianTransposeParameters) -> SolveResult {
+	if chain.joints.is_empty() {
+		return SolveResult::new(chain, target, Vec::new(), 0, parameters.distance_threshold, parameters.orientation_threshold, false)
+	}
 
 	let mut angles = chain.angles.clone();
 
-	for _ in 0..MAX_ITERATIONS {
+	let mut iterations = 0;
+
+	for _ in 0..parameters.max_iterations {
 		let mut cumulative_transforms = vec![Matrix4::identity()];
 		cumulative_transforms.extend(chain.cumulative_transforms_with_angles(&angles).into_iter());
 
-		let end = (cumulative_transforms.last().unwrap() * Vector4::unit_w()).truncate();
+		let end_transform = *cumulative_transforms.last().unwrap();
+		let end = (end_transform * Vector4::unit_w()).truncate();
+
+		let desired_change      = target.position - end;
+		let desired_orientation = target.orientation_error(&end_transform);
 
-		if (end - target).magnitude() < DISTANCE_THRESHOLD { break }
+		if desired_change.magnitude()      < parameters.distance_threshold
+		&& desired_orientation.magnitude() < parameters.orientation_threshold { break }
 
-		let desired_change = target - end;
+		iterations += 1;
 
 		for (idx, (transform, joint)) in cumulative_transforms.iter().zip(chain.joints.iter()).enumerate() {
-			let position = (transform * Vector4::unit_w()).truncate();
+			// desired_orientation is zero without an orientation target so the angular part drops out
+			//
+			let (j, a) = joint.jacobian_column(transform, end);
+
+			angles[idx] = joint.clamp(angles[idx] + parameters.step_size * (j.dot(desired_change) + a.dot(desired_orientation)));
+		}
+	}
+	SolveResult::new(chain, target, angles, iterations, parameters.distance_threshold, parameters.orientation_threshold, false)
+}
+
+pub fn jacobian_transpose_skeleton(skeleton: &Skeleton, parameters: &JacobianTransposeParameters) -> SolveResult {
+	let mut angles = skeleton.angles.clone();
+
+	let mut iterations = 0;
+
+	for _ in 0..parameters.max_iterations {
+		if skeleton.residuals_with_angles(&angles).iter().all(
+			|&(position, orientation)| position < parameters.distance_threshold && orientation < parameters.orientation_threshold
+		) { break }
+
+		iterations += 1;
 
-			let axis = (transform * joint.axis.to_vector3().extend(0f32)).truncate();
+		let (jacobian, error) = skeleton.stacked_jacobian_with_angles(&angles);
 
-			let j = axis.cross(target - position);
+		let delta = jacobian.transpose() * error;
 
-			angles[idx] += FUDGE * j.dot(desired_change);
+		for ((angle, delta), bone) in angles.iter_mut().zip(delta.iter()).zip(skeleton.bones.iter()) {
+			*angle = bone.joint.clamp(*angle + parameters.step_size * delta);
 		}
 	}
-	angles
+	SolveResult::from_skeleton(skeleton, angles, iterations, parameters.distance_threshold, parameters.orientation_threshold, false)
 }
@@ -1,11 +1,28 @@
-pub use self::chain::{Axis, Chain, Joint};
+pub use self::chain::{Axis, Chain, Joint, JointKind, BONE_HALF_WIDTH};
+pub use self::easing::{Easing};
+pub use self::solve_result::{SolveResult, SolveStatus};
+pub use self::skeleton::{Bone, Effector, Skeleton};
+pub use self::solver::{Solver, SkeletonSolver};
+pub use self::target::{Target};
 pub use self::updater::{State, Transition, update};
-pub use self::jacobian_pseudo_inverse::{jacobian_pseudo_inverse};
-pub use self::jacobian_transpose::{jacobian_transpose};
-pub use self::cyclic_coordinate_descent::{cyclic_coordinate_descent};
+pub use self::jacobian_pseudo_inverse::{JacobianPseudoInverseParameters, jacobian_pseudo_inverse, jacobian_pseudo_inverse_skeleton};
+pub use self::jacobian_transpose::{JacobianTransposeParameters, jacobian_transpose, jacobian_transpose_skeleton};
+pub use self::cyclic_coordinate_descent::{CyclicCoordinateDescentParameters, cyclic_coordinate_descent};
+pub use self::damped_least_squares::{DampedLeastSquaresParameters, damped_least_squares, damped_least_squares_skeleton};
+pub use self::forward_and_backward_reaching::{ForwardAndBackwardReachingParameters, forward_and_backward_reaching};
 
 mod chain;
+mod easing;
+mod solve_result;
+mod skeleton;
+mod solver;
+mod target;
 mod cyclic_coordinate_descent;
 mod jacobian_transpose;
 mod jacobian_pseudo_inverse;
-pub mod updater;
\ No newline at end of file
+mod damped_least_squares;
+mod forward_and_backward_reaching;
+pub mod updater;
+
+#[cfg(test)]
+mod tests;
\ No newline at end of file
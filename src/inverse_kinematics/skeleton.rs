@@ -0,0 +1,161 @@
+use cgmath::{InnerSpace, Matrix4, SquareMatrix, Vector3, Vector4};
+use nalgebra::{DMatrix, DVector};
+
+use inverse_kinematics::{Joint, SkeletonSolver, Target};
+
+
+#[derive(Copy, Clone)]
+pub struct Bone {
+	pub joint:  Joint,
+	pub parent: Option<usize>, // always an earlier bone, so a single forward pass resolves every transform
+}
+
+// weight sets the priority of this effector relative to the others on the same skeleton
+//
+#[derive(Copy, Clone, PartialEq)]
+pub struct Effector {
+	pub bone:   usize,
+	pub target: Target,
+	pub weight: f32,
+}
+
+#[derive(Clone)]
+pub struct Skeleton {
+	pub bones:     Vec<Bone>,
+	pub angles:    Vec<f32>,
+	pub effectors: Vec<Effector>,
+	pub position:  Vector3<f32>,
+	pub solver:    SkeletonSolver,
+}
+
+impl Skeleton {
+	pub fn new(bones: Vec<Bone>, angles: Vec<f32>, effectors: Vec<Effector>, position: Vector3<f32>, solver: SkeletonSolver) -> Skeleton {
+		assert!(bones.len() == angles.len(), "Skeleton needs one angle per bone");
+		for (idx, bone) in bones.iter().enumerate() {
+			if let Some(parent) = bone.parent { assert!(parent < idx, "Skeleton bones must come after their parents") }
+		}
+		for effector in &effectors { assert!(effector.bone < bones.len(), "Effector attached to a missing bone") }
+
+		Skeleton {
+			bones:     bones,
+			angles:    angles,
+			effectors: effectors,
+			position:  position,
+			solver:    solver,
+		}
+	}
+
+	pub fn visible_joint_transforms(&self) -> Vec<Matrix4<f32>> {
+		let transforms = self.cumulative_transforms();
+
+		self.bones.iter().zip(self.angles.iter()).enumerate().filter(|&(_, (bone, _))| bone.joint.length() != 0.0).map(
+			|(idx, (bone, angle))| self.parent_transform(&transforms, idx) * bone.joint.bone_transform(*angle)
+		).collect()
+	}
+
+	pub fn cumulative_transforms(&self) -> Vec<Matrix4<f32>> {
+		self.cumulative_transforms_with_angles(&self.angles)
+	}
+
+	// same as Chain::cumulative_transforms_with_angles except each bone accumulates onto its parent instead of its predecessor
+	//
+	pub fn cumulative_transforms_with_angles(&self, angles: &[f32]) -> Vec<Matrix4<f32>> {
+		let mut transforms: Vec<Matrix4<f32>> = Vec::with_capacity(self.bones.len());
+
+		for (idx, (bone, angle)) in self.bones.iter().zip(angles.iter()).enumerate() {
+			let transform = self.parent_transform(&transforms, idx) * bone.joint.transform(*angle);
+			transforms.push(transform);
+		}
+		transforms
+	}
+
+	// bones from the root down to and including `bone`
+	//
+	pub fn path_to(&self, bone: usize) -> Vec<usize> {
+		let mut path = vec![bone];
+		while let Some(parent) = self.bones[*path.last().unwrap()].parent { path.push(parent) }
+		path.reverse();
+		path
+	}
+
+	pub fn reach_to(&self, bone: usize) -> f32 {
+		self.path_to(bone).iter().map(|&idx| self.bones[idx].joint.reach()).sum()
+	}
+
+	// (position, orientation) residuals for each effector
+	//
+	pub fn residuals_with_angles(&self, angles: &[f32]) -> Vec<(f32, f32)> {
+		let transforms = self.cumulative_transforms_with_angles(angles);
+
+		self.effectors.iter().map(|effector| {
+			let transform = transforms[effector.bone];
+			(
+				((transform * Vector4::unit_w()).truncate() - effector.target.position).magnitude(),
+				effector.target.orientation_error(&transform).magnitude(),
+			)
+		}).collect()
+	}
+
+	// every effector contributes 3 rows (6 with an orientation target) scaled by its weight,
+	// a bone's column is only non zero in the rows of effectors it is an ancestor of
+	//
+	pub fn stacked_jacobian_with_angles(&self, angles: &[f32]) -> (DMatrix<f32>, DVector<f32>) {
+		let transforms = self.cumulative_transforms_with_angles(angles);
+
+		let rows: usize = self.effectors.iter().map(|effector| effector_rows(effector)).sum();
+
+		let mut jacobian = DMatrix::from_element(rows, self.bones.len(), 0f32);
+		let mut error = DVector::from_element(rows, 0f32);
+
+		let mut row = 0;
+		for effector in &self.effectors {
+			let weight = effector.weight;
+			let end_transform = transforms[effector.bone];
+			let end = (end_transform * Vector4::unit_w()).truncate();
+
+			let desired_change      = (effector.target.position - end) * weight;
+			let desired_orientation = effector.target.orientation_error(&end_transform) * weight;
+			let oriented = effector_rows(effector) == 6;
+
+			unsafe {
+				*error.get_unchecked_mut(row + 0, 0) = desired_change.x;
+				*error.get_unchecked_mut(row + 1, 0) = desired_change.y;
+				*error.get_unchecked_mut(row + 2, 0) = desired_change.z;
+				if oriented {
+					*error.get_unchecked_mut(row + 3, 0) = desired_orientation.x;
+					*error.get_unchecked_mut(row + 4, 0) = desired_orientation.y;
+					*error.get_unchecked_mut(row + 5, 0) = desired_orientation.z;
+				}
+			}
+
+			for idx in self.path_to(effector.bone) {
+				let (j, a) = self.bones[idx].joint.jacobian_column(&self.parent_transform(&transforms, idx), end);
+				let j = j * weight;
+				let a = a * weight;
+				unsafe {
+					*jacobian.get_unchecked_mut(row + 0, idx) = j.x;
+					*jacobian.get_unchecked_mut(row + 1, idx) = j.y;
+					*jacobian.get_unchecked_mut(row + 2, idx) = j.z;
+					if oriented {
+						*jacobian.get_unchecked_mut(row + 3, idx) = a.x;
+						*jacobian.get_unchecked_mut(row + 4, idx) = a.y;
+						*jacobian.get_unchecked_mut(row + 5, idx) = a.z;
+					}
+				}
+			}
+			row += effector_rows(effector);
+		}
+		(jacobian, error)
+	}
+
+	fn parent_transform(&self, transforms: &[Matrix4<f32>], bone: usize) -> Matrix4<f32> {
+		match self.bones[bone].parent {
+			Some(parent) => transforms[parent],
+			None         => Matrix4::identity(),
+		}
+	}
+}
+
+fn effector_rows(effector: &Effector) -> usize {
+	if effector.target.orientation.is_some() { 6 } else { 3 }
+}
@@ -0,0 +1,75 @@
+use cgmath::{InnerSpace, Vector4};
+
+use inverse_kinematics::{Chain, Skeleton, Target};
+
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum SolveStatus {
+	Converged,
+	Incomplete,  // ran out of iterations while the target was still within reach
+	Unreachable, // target is further from the root than the chain can stretch
+	Singular,    // the solver hit a configuration it could not step out of
+}
+
+#[derive(Clone, PartialEq)]
+pub struct SolveResult {
+	pub angles:               Vec<f32>,
+	pub residual:             f32,
+	pub orientation_residual: f32, // radians, zero for position only targets
+	pub iterations:           usize,
+	pub status:               SolveStatus,
+}
+
+impl SolveResult {
+	pub fn new(chain: &Chain, target: Target, angles: Vec<f32>, iterations: usize, distance_threshold: f32, orientation_threshold: f32, singular: bool) -> SolveResult {
+		let transform = chain.end_effector_transform_with_angles(&angles);
+
+		let residual             = ((transform * Vector4::unit_w()).truncate() - target.position).magnitude();
+		let orientation_residual = target.orientation_error(&transform).magnitude();
+
+		let converged = residual < distance_threshold && orientation_residual < orientation_threshold;
+		let unreachable = target.position.magnitude() > chain.reach();
+
+		SolveResult {
+			angles:               angles,
+			residual:             residual,
+			orientation_residual: orientation_residual,
+			iterations:           iterations,
+			status:               status(converged, singular, unreachable),
+		}
+	}
+
+	// residuals are the worst over all of the skeleton's effectors
+	//
+	pub fn from_skeleton(skeleton: &Skeleton, angles: Vec<f32>, iterations: usize, distance_threshold: f32, orientation_threshold: f32, singular: bool) -> SolveResult {
+		let residuals = skeleton.residuals_with_angles(&angles);
+
+		let residual             = residuals.iter().fold(0f32, |max, &(position, _)|    max.max(position));
+		let orientation_residual = residuals.iter().fold(0f32, |max, &(_, orientation)| max.max(orientation));
+
+		let converged = residual < distance_threshold && orientation_residual < orientation_threshold;
+		let unreachable = skeleton.effectors.iter().any(
+			|effector| effector.target.position.magnitude() > skeleton.reach_to(effector.bone)
+		);
+
+		SolveResult {
+			angles:               angles,
+			residual:             residual,
+			orientation_residual: orientation_residual,
+			iterations:           iterations,
+			status:               status(converged, singular, unreachable),
+		}
+	}
+}
+
+fn status(converged: bool, singular: bool, unreachable: bool) -> SolveStatus {
+	if converged {
+		SolveStatus::Converged
+	} else if singular {
+		SolveStatus::Singular
+	} else if unreachable {
+		SolveStatus::Unreachable
+	} else {
+		SolveStatus::Incomplete
+	}
+}
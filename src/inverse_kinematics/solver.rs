@@ -0,0 +1,47 @@
+use inverse_kinematics::{Chain, Skeleton, SolveResult, Target};
+use inverse_kinematics::{CyclicCoordinateDescentParameters, cyclic_coordinate_descent};
+use inverse_kinematics::{DampedLeastSquaresParameters, damped_least_squares, damped_least_squares_skeleton};
+use inverse_kinematics::{ForwardAndBackwardReachingParameters, forward_and_backward_reaching};
+use inverse_kinematics::{JacobianPseudoInverseParameters, jacobian_pseudo_inverse, jacobian_pseudo_inverse_skeleton};
+use inverse_kinematics::{JacobianTransposeParameters, jacobian_transpose, jacobian_transpose_skeleton};
+
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Solver {
+	CyclicCoordinateDescent(CyclicCoordinateDescentParameters),
+	JacobianTranspose(JacobianTransposeParameters),
+	JacobianPseudoInverse(JacobianPseudoInverseParameters),
+	DampedLeastSquares(DampedLeastSquaresParameters),
+	ForwardAndBackwardReaching(ForwardAndBackwardReachingParameters),
+}
+
+impl Solver {
+	pub fn solve(&self, chain: &Chain, target: Target) -> SolveResult {
+		match *self {
+			Solver::CyclicCoordinateDescent(ref parameters)    => cyclic_coordinate_descent(chain, target, parameters),
+			Solver::JacobianTranspose(ref parameters)          => jacobian_transpose(chain, target, parameters),
+			Solver::JacobianPseudoInverse(ref parameters)      => jacobian_pseudo_inverse(chain, target, parameters),
+			Solver::DampedLeastSquares(ref parameters)         => damped_least_squares(chain, target, parameters),
+			Solver::ForwardAndBackwardReaching(ref parameters) => forward_and_backward_reaching(chain, target, parameters),
+		}
+	}
+}
+
+// only the jacobian methods know how to stack several effectors into one system
+//
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum SkeletonSolver {
+	JacobianTranspose(JacobianTransposeParameters),
+	JacobianPseudoInverse(JacobianPseudoInverseParameters),
+	DampedLeastSquares(DampedLeastSquaresParameters),
+}
+
+impl SkeletonSolver {
+	pub fn solve(&self, skeleton: &Skeleton) -> SolveResult {
+		match *self {
+			SkeletonSolver::JacobianTranspose(ref parameters)     => jacobian_transpose_skeleton(skeleton, parameters),
+			SkeletonSolver::JacobianPseudoInverse(ref parameters) => jacobian_pseudo_inverse_skeleton(skeleton, parameters),
+			SkeletonSolver::DampedLeastSquares(ref parameters)    => damped_least_squares_skeleton(skeleton, parameters),
+		}
+	}
+}
@@ -0,0 +1,49 @@
+use cgmath::{InnerSpace, Matrix3, Matrix4, Quaternion, Vector3};
+
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Target {
+	pub position:    Vector3<f32>,
+	pub orientation: Option<Quaternion<f32>>, // None leaves the end effector free to rotate
+}
+
+impl Target {
+	pub fn from_position(position: Vector3<f32>) -> Target {
+		Target {
+			position:    position,
+			orientation: None,
+		}
+	}
+
+	#[cfg(test)]
+	pub fn with_orientation(position: Vector3<f32>, orientation: Quaternion<f32>) -> Target {
+		Target {
+			position:    position,
+			orientation: Some(orientation),
+		}
+	}
+
+	// rotation (as axis * angle) taking the transform's orientation to ours, zero when unconstrained
+	//
+	pub fn orientation_error(&self, transform: &Matrix4<f32>) -> Vector3<f32> {
+		const EPSILON: f32 = 0.0001;
+
+		let orientation = match self.orientation {
+			Some(orientation) => orientation,
+			None              => return Vector3::new(0.0, 0.0, 0.0),
+		};
+
+		let current = Quaternion::from(Matrix3::from_cols(
+			transform.x.truncate(),
+			transform.y.truncate(),
+			transform.z.truncate()
+		));
+		let error = orientation * current.conjugate();
+		let error = if error.s < 0.0 { -error } else { error }; // the short way around
+
+		let sin = error.v.magnitude();
+
+		if sin < EPSILON { error.v * 2.0 }
+		else             { error.v * (2.0 * sin.atan2(error.s) / sin) }
+	}
+}
@@ -0,0 +1,475 @@
+use std::f32::consts::{PI, FRAC_PI_2};
+
+use cgmath::{InnerSpace, Matrix3, Matrix4, Quaternion, Vector3, Vector4};
+use rand::{SeedableRng, StdRng};
+use rand::distributions::{IndependentSample, Range};
+
+use inverse_kinematics::{Axis, Bone, Chain, Easing, Effector, Joint, JointKind, Skeleton, SkeletonSolver, SolveStatus, Solver, State, Target, Transition, updater};
+use inverse_kinematics::{CyclicCoordinateDescentParameters, DampedLeastSquaresParameters, ForwardAndBackwardReachingParameters};
+use inverse_kinematics::{JacobianPseudoInverseParameters, JacobianTransposeParameters};
+
+
+// everything in here is plain math on chains, none of it needs a window or a GL context
+//
+
+const PROPERTY_CASES: usize = 50;
+
+fn arm() -> Vec<Joint> {
+	vec![
+		Joint::revolute(Axis::Y.to_vector3(), 0.0),
+		Joint::revolute(Axis::X.to_vector3(), 3.0).with_limits(-FRAC_PI_2, FRAC_PI_2),
+		Joint::revolute(Axis::X.to_vector3(), 3.0).with_limits(-0.75 * PI, 0.75 * PI),
+		Joint::revolute(Axis::X.to_vector3(), 3.0).with_limits(-0.75 * PI, 0.75 * PI),
+	]
+}
+
+fn chain(joints: Vec<Joint>, angles: Vec<f32>, solver: Solver) -> Chain {
+	Chain {
+		joints:   joints,
+		angles:   angles,
+		state:    State::Done,
+		position: Vector3::new(0.0, 0.0, 0.0),
+		solver:   solver,
+		easing:   Easing::Linear,
+	}
+}
+
+// generous iteration counts so the tests are about correctness rather than speed
+//
+fn solvers() -> Vec<Solver> {
+	vec![
+		Solver::CyclicCoordinateDescent(CyclicCoordinateDescentParameters { max_iterations: 500, .. Default::default() }),
+		Solver::JacobianTranspose(JacobianTransposeParameters { max_iterations: 500, step_size: 0.005, .. Default::default() }),
+		Solver::JacobianPseudoInverse(JacobianPseudoInverseParameters { max_iterations: 500, .. Default::default() }),
+		Solver::DampedLeastSquares(DampedLeastSquaresParameters { max_iterations: 500, .. Default::default() }),
+		Solver::ForwardAndBackwardReaching(ForwardAndBackwardReachingParameters { max_iterations: 200, .. Default::default() }),
+	]
+}
+
+fn end_position(chain: &Chain, angles: &[f32]) -> Vector3<f32> {
+	chain.end_effector_with_angles(angles)
+}
+
+fn end_orientation(transform: &Matrix4<f32>) -> Quaternion<f32> {
+	Quaternion::from(Matrix3::from_cols(transform.x.truncate(), transform.y.truncate(), transform.z.truncate()))
+}
+
+fn is_finite(angles: &[f32]) -> bool {
+	angles.iter().all(|angle| angle.is_finite())
+}
+
+fn within_limits(joints: &[Joint], angles: &[f32]) -> bool {
+	joints.iter().zip(angles.iter()).all(|(joint, &angle)| joint.clamp(angle) == angle)
+}
+
+fn rng(seed: usize) -> StdRng {
+	let seed: &[_] = &[seed, 7, 7, 7];
+	SeedableRng::from_seed(seed)
+}
+
+fn random_angles(rng: &mut StdRng, joints: &[Joint]) -> Vec<f32> {
+	joints.iter().map(|joint| {
+		let min = joint.min_angle.max(-PI);
+		let max = joint.max_angle.min(PI);
+		if min < max { Range::new(min, max).ind_sample(rng) } else { min }
+	}).collect()
+}
+
+// a mix of revolute joints about arbitrary axes (some of them zero length) and the odd prismatic one
+//
+fn random_joints(rng: &mut StdRng) -> Vec<Joint> {
+	let count     = Range::new(1, 7);
+	let component = Range::new(-1f32, 1f32);
+	let length    = Range::new(0f32, 3f32);
+	let limit     = Range::new(0.25 * PI, PI);
+	let kind      = Range::new(0, 5);
+
+	(0..count.ind_sample(rng)).map(|_| {
+		let mut axis = Vector3::new(component.ind_sample(rng), component.ind_sample(rng), component.ind_sample(rng));
+		if axis.magnitude() < 0.1 { axis = Vector3::unit_z() }
+
+		let offset = Vector3::new(component.ind_sample(rng), component.ind_sample(rng), component.ind_sample(rng));
+		let offset = if offset.magnitude() < 0.1 { Vector3::unit_y() } else { offset.normalize() };
+
+		if kind.ind_sample(rng) == 0 {
+			Joint::prismatic(axis, offset).with_limits(-1.0, 1.0)
+		} else {
+			let limit = limit.ind_sample(rng);
+			Joint::revolute(axis, 0.0).with_offset(offset * length.ind_sample(rng)).with_limits(-limit, limit)
+		}
+	}).collect()
+}
+
+#[test]
+fn reachable_targets_are_reached() {
+	let joints = arm();
+	let start  = vec![0.0, 0.0, 0.0, 0.1];
+	let goal   = vec![0.5, 0.3, 0.6, 0.4];
+
+	for solver in solvers() {
+		let chain = chain(joints.clone(), start.clone(), solver);
+		let target = Target::from_position(end_position(&chain, &goal));
+		let initial_residual = (end_position(&chain, &start) - target.position).magnitude();
+
+		let result = solver.solve(&chain, target);
+
+		assert!(is_finite(&result.angles), "{:?} produced a NaN", solver);
+		assert!(within_limits(&chain.joints, &result.angles), "{:?} left the joint limits", solver);
+		assert!(((end_position(&chain, &result.angles) - target.position).magnitude() - result.residual).abs() < 0.0001);
+
+		match solver {
+			Solver::CyclicCoordinateDescent(_) | Solver::DampedLeastSquares(_) => {
+				assert!(result.status == SolveStatus::Converged, "{:?} missed a reachable target by {}", solver, result.residual);
+			},
+			Solver::JacobianPseudoInverse(_) => {
+				assert!(result.status == SolveStatus::Converged || result.status == SolveStatus::Singular);
+			},
+			_ => {
+				assert!(result.residual < initial_residual, "{:?} made no progress", solver);
+			},
+		}
+	}
+}
+
+#[test]
+fn unreachable_targets_are_reported() {
+	let joints = arm();
+	let start  = vec![0.0, 0.2, 0.2, 0.2];
+
+	for solver in solvers() {
+		let chain = chain(joints.clone(), start.clone(), solver);
+		let distance = chain.reach() * 2.0;
+		let target = Target::from_position(Vector3::new(1.0, 1.0, 1.0).normalize() * distance);
+
+		let result = solver.solve(&chain, target);
+
+		assert!(is_finite(&result.angles), "{:?} produced a NaN", solver);
+		assert!(within_limits(&chain.joints, &result.angles), "{:?} left the joint limits", solver);
+		assert!(result.residual >= distance - chain.reach() - 0.001);
+		assert!(
+			result.status == SolveStatus::Unreachable || result.status == SolveStatus::Singular,
+			"{:?} reported {:?} for an unreachable target", solver, result.status
+		);
+	}
+}
+
+// starting on the target must not move anything, with or without an orientation constraint
+//
+#[test]
+fn solved_chains_stay_put() {
+	let joints = arm();
+	let angles = vec![0.4, -0.3, 0.9, 0.2];
+
+	for solver in solvers() {
+		let chain = chain(joints.clone(), angles.clone(), solver);
+		let transform = chain.end_effector_transform_with_angles(&angles);
+
+		let targets = [
+			Target::from_position(end_position(&chain, &angles)),
+			Target::with_orientation(end_position(&chain, &angles), end_orientation(&transform)),
+		];
+		for &target in &targets {
+			let result = solver.solve(&chain, target);
+
+			assert!(result.status == SolveStatus::Converged, "{:?} reported {:?}", solver, result.status);
+			assert_eq!(result.iterations, 0);
+			assert_eq!(result.angles, angles);
+		}
+	}
+}
+
+// nudging a solved target should only nudge the angles
+//
+#[test]
+fn small_target_moves_give_small_angle_changes() {
+	let solver = Solver::DampedLeastSquares(DampedLeastSquaresParameters { max_iterations: 500, .. Default::default() });
+	let goal = vec![0.5, 0.3, 0.6, 0.4];
+
+	let first = {
+		let chain = chain(arm(), vec![0.0, 0.0, 0.0, 0.1], solver);
+		let target = Target::from_position(end_position(&chain, &goal));
+		solver.solve(&chain, target)
+	};
+	assert!(first.status == SolveStatus::Converged);
+
+	let chain = chain(arm(), first.angles.clone(), solver);
+	let target = Target::from_position(end_position(&chain, &first.angles) + Vector3::new(0.05, 0.0, 0.0));
+	let second = solver.solve(&chain, target);
+
+	assert!(second.status == SolveStatus::Converged);
+	for (before, after) in first.angles.iter().zip(second.angles.iter()) {
+		assert!((after - before).abs() < 0.5, "angle jumped from {} to {}", before, after);
+	}
+}
+
+// a fully straight chain aimed along itself or at its own root is where the jacobians and CCD's perpendiculars degenerate
+//
+#[test]
+fn degenerate_targets_do_not_produce_nans() {
+	let joints = arm();
+	let straight = vec![0.0, 0.0, 0.0, 0.0];
+
+	for solver in solvers() {
+		let chain = chain(joints.clone(), straight.clone(), solver);
+		let targets = [
+			Target::from_position(Vector3::new(0.0, 0.0, 0.0)),
+			Target::from_position(Vector3::new(0.0, 12.0, 0.0)),
+			Target::from_position(Vector3::new(0.0, -9.0, 0.0)),
+		];
+		for &target in &targets {
+			let result = solver.solve(&chain, target);
+
+			assert!(is_finite(&result.angles), "{:?} produced a NaN for {:?}", solver, target);
+			assert!(result.residual.is_finite());
+			assert!(within_limits(&chain.joints, &result.angles));
+		}
+	}
+
+	let empty = chain(Vec::new(), Vec::new(), Solver::DampedLeastSquares(Default::default()));
+	for solver in solvers() {
+		let result = solver.solve(&empty, Target::from_position(Vector3::new(1.0, 0.0, 0.0)));
+		assert!(result.angles.is_empty());
+		assert!(result.status == SolveStatus::Unreachable);
+	}
+}
+
+#[test]
+fn prismatic_joints_slide_to_the_target() {
+	let joints = vec![
+		Joint::revolute(Axis::Z.to_vector3(), 2.0).with_limits(-PI, PI),
+		Joint::prismatic(Axis::Y.to_vector3(), Vector3::new(0.0, 1.0, 0.0)).with_limits(0.0, 3.0),
+	];
+	let solver = Solver::DampedLeastSquares(DampedLeastSquaresParameters { max_iterations: 500, .. Default::default() });
+	let chain = chain(joints, vec![0.0, 0.0], solver);
+
+	assert_eq!(chain.reach(), 6.0);
+	assert!(chain.joints[1].kind == JointKind::Prismatic);
+
+	let target = Target::from_position(Vector3::new(0.0, 4.5, 0.0));
+	let result = solver.solve(&chain, target);
+
+	assert!(result.status == SolveStatus::Converged, "missed by {}", result.residual);
+	assert!((result.angles[1] - 1.5).abs() < 0.05);
+}
+
+#[test]
+fn seeking_moves_smoothly_onto_the_solution() {
+	const FRAMES: u16 = 30;
+
+	let solver = Solver::DampedLeastSquares(Default::default());
+	let chain = chain(arm(), vec![0.0, 0.0, 0.0, 0.1], solver);
+	let target = Target::from_position(end_position(&chain, &[0.5, 0.3, 0.6, 0.4]));
+
+	let mut current = updater::update(&chain, Transition::NewTarget { target: target, num_transition_frames: FRAMES });
+
+	let (base, goal) = match current.state {
+		State::Seeking { ref base_angles, ref target_angles, .. } => (base_angles.clone(), target_angles.clone()),
+		_ => panic!("a new target should start seeking"),
+	};
+	let max_steps: Vec<f32> = base.iter().zip(goal.iter()).map(|(b, g)| (g - b).abs() / FRAMES as f32 + 0.0001).collect();
+
+	let mut previous = chain.angles.clone();
+	for _ in 0..FRAMES {
+		assert!(is_finite(&current.angles));
+		for ((before, after), max_step) in previous.iter().zip(current.angles.iter()).zip(max_steps.iter()) {
+			assert!((after - before).abs() <= *max_step, "jumped from {} to {}", before, after);
+		}
+		previous = current.angles.clone();
+
+		if let State::Waiting { .. } = current.state { break }
+		current = updater::update(&current, Transition::Update);
+	}
+
+	match current.state {
+		State::Waiting { .. } => (),
+		_ => panic!("seeking should finish within its transition frames"),
+	}
+	for (angle, goal) in current.angles.iter().zip(goal.iter()) {
+		assert!((angle - goal).abs() < 0.0001);
+	}
+}
+
+// properties over random chains: whatever the solver does it has to stay finite, inside the limits
+// and report a status that agrees with its residual, and levenberg-marquardt can never get worse
+//
+#[test]
+fn random_chains_keep_their_invariants() {
+	let mut rng = rng(1);
+
+	for case in 0..PROPERTY_CASES {
+		let joints = random_joints(&mut rng);
+		let start  = random_angles(&mut rng, &joints);
+		let goal   = random_angles(&mut rng, &joints);
+
+		for solver in solvers() {
+			let chain = chain(joints.clone(), start.clone(), solver);
+			let transform = chain.end_effector_transform_with_angles(&goal);
+			let position = (transform * Vector4::unit_w()).truncate();
+
+			let targets = [
+				Target::from_position(position),
+				Target::with_orientation(position, end_orientation(&transform)),
+			];
+			for &target in &targets {
+				let result = solver.solve(&chain, target);
+
+				assert!(is_finite(&result.angles), "case {}: {:?} produced a NaN", case, solver);
+				assert!(within_limits(&joints, &result.angles), "case {}: {:?} left the joint limits", case, solver);
+				assert_eq!(result.angles.len(), joints.len());
+
+				let residual = (end_position(&chain, &result.angles) - target.position).magnitude();
+				assert!((residual - result.residual).abs() < 0.0001);
+
+				let converged = result.residual < 0.01 && result.orientation_residual < 0.01;
+				assert!(converged == (result.status == SolveStatus::Converged), "case {}: {:?} status disagrees with residual", case, solver);
+
+				// the target came from a reachable pose
+				//
+				assert!(result.status != SolveStatus::Unreachable);
+
+				if let Solver::DampedLeastSquares(_) = solver {
+					if target.orientation.is_none() {
+						let initial = (end_position(&chain, &start) - target.position).magnitude();
+						assert!(result.residual <= initial + 0.0001, "case {}: got worse, {} from {}", case, result.residual, initial);
+					}
+				}
+			}
+		}
+	}
+}
+
+#[test]
+fn random_skeletons_keep_their_invariants() {
+	let mut rng = rng(2);
+	let solvers = [
+		SkeletonSolver::JacobianTranspose(Default::default()),
+		SkeletonSolver::JacobianPseudoInverse(Default::default()),
+		SkeletonSolver::DampedLeastSquares(Default::default()),
+	];
+
+	for case in 0..PROPERTY_CASES {
+		let joints = random_joints(&mut rng);
+		let parents: Vec<Option<usize>> = (0..joints.len()).map(
+			|idx| if idx == 0 { None } else { Some(Range::new(0, idx).ind_sample(&mut rng)) }
+		).collect();
+		let bones: Vec<Bone> = joints.iter().zip(parents.iter()).map(|(&joint, &parent)| Bone { joint: joint, parent: parent }).collect();
+		let start = random_angles(&mut rng, &joints);
+		let goal  = random_angles(&mut rng, &joints);
+
+		for &solver in &solvers {
+			let posed = Skeleton::new(bones.clone(), goal.clone(), Vec::new(), Vector3::new(0.0, 0.0, 0.0), solver);
+			let transforms = posed.cumulative_transforms();
+
+			// both ends of the tree, which might be the same bone
+			//
+			let effectors = vec![
+				Effector { bone: 0,                 target: Target::from_position((transforms[0] * Vector4::unit_w()).truncate()),                 weight: 1.0 },
+				Effector { bone: joints.len() - 1, target: Target::from_position((transforms[joints.len() - 1] * Vector4::unit_w()).truncate()), weight: 0.5 },
+			];
+			let skeleton = Skeleton::new(bones.clone(), start.clone(), effectors, Vector3::new(0.0, 0.0, 0.0), solver);
+
+			let result = solver.solve(&skeleton);
+
+			assert!(is_finite(&result.angles), "case {}: {:?} produced a NaN", case, solver);
+			assert!(within_limits(&joints, &result.angles), "case {}: {:?} left the joint limits", case, solver);
+			assert!(result.residual.is_finite());
+			assert!(result.status != SolveStatus::Unreachable);
+		}
+	}
+}
+
+#[test]
+fn easing_curves_start_and_end_on_the_transition() {
+	let easings = [
+		Easing::Linear,
+		Easing::EaseInOut,
+		Easing::CubicHermite { start_tangent: 0.0, end_tangent: 0.0 },
+		Easing::CubicHermite { start_tangent: 1.0, end_tangent: 1.0 },
+		Easing::CriticallyDampedSpring { omega: 6.0 },
+	];
+
+	for easing in &easings {
+		assert!(easing.ease(0.0).abs() < 0.0001, "{:?} does not start at 0", easing);
+		assert!((easing.ease(1.0) - 1.0).abs() < 0.0001, "{:?} does not end at 1", easing);
+
+		let mut previous = 0.0;
+		for step in 1..101 {
+			let eased = easing.ease(step as f32 / 100.0);
+			assert!(eased >= previous - 0.0001, "{:?} moved backwards", easing);
+			previous = eased;
+		}
+	}
+}
+
+#[test]
+fn seeking_respects_velocity_and_acceleration_limits() {
+	const MAX_VELOCITY:     f32 = 0.02;
+	const MAX_ACCELERATION: f32 = 0.002;
+
+	let joints: Vec<Joint> = arm().into_iter().map(|joint| joint.with_velocity_limits(MAX_VELOCITY, MAX_ACCELERATION)).collect();
+	let solver = Solver::DampedLeastSquares(Default::default());
+	let chain = Chain { easing: Easing::EaseInOut, .. chain(joints, vec![0.0, 0.0, 0.0, 0.1], solver) };
+	let target = Target::from_position(end_position(&chain, &[1.5, 0.3, 0.6, 0.4]));
+
+	let mut current = updater::update(&chain, Transition::NewTarget { target: target, num_transition_frames: 10 });
+	let mut previous_angles = chain.angles.clone();
+	let mut previous_velocities = vec![0.0; chain.joints.len()];
+
+	for _ in 0..10000 {
+		let velocities: Vec<f32> = current.angles.iter().zip(previous_angles.iter()).map(|(a, b)| a - b).collect();
+
+		for (velocity, previous) in velocities.iter().zip(previous_velocities.iter()) {
+			assert!(velocity.abs() <= MAX_VELOCITY + 0.0001, "velocity {} over the limit", velocity);
+			assert!((velocity - previous).abs() <= MAX_ACCELERATION + 0.0001, "acceleration {} over the limit", velocity - previous);
+		}
+
+		if let State::Waiting { .. } = current.state { break }
+
+		previous_angles = current.angles.clone();
+		previous_velocities = velocities;
+		current = updater::update(&current, Transition::Update);
+	}
+
+	match current.state {
+		State::Waiting { .. } => (),
+		_ => panic!("velocity limited joints never arrived"),
+	}
+}
+
+#[test]
+fn tracking_follows_a_moving_target() {
+	let solver = Solver::DampedLeastSquares(Default::default());
+	let mut current = chain(arm(), vec![0.0, 0.0, 0.0, 0.1], solver);
+
+	for frame in 0..300 {
+		let angle = frame as f32 * 0.01;
+		let target = Target::from_position(Vector3::new(5.0 * angle.cos(), 3.0, 5.0 * angle.sin()));
+
+		current = updater::update(&current, Transition::Track { target: target });
+
+		match current.state {
+			State::Tracking { ref result, .. } => {
+				assert!(is_finite(&current.angles));
+				assert!(result.angles.iter().zip(current.angles.iter()).all(|(a, b)| (a - b).abs() < 0.0001));
+				if frame > 10 {
+					assert!(result.status == SolveStatus::Converged, "lost the target on frame {} by {}", frame, result.residual);
+				}
+			},
+			_ => panic!("tracking should stay in Tracking"),
+		}
+	}
+
+	// an Update keeps chasing the last target it was given
+	//
+	let tracked = updater::update(&current, Transition::Update);
+	match tracked.state {
+		State::Tracking { .. } => (),
+		_ => panic!("tracking should stay in Tracking"),
+	}
+
+	let seeking = updater::update(&tracked, Transition::NewTarget { target: Target::from_position(Vector3::new(0.0, 6.0, 3.0)), num_transition_frames: 10 });
+	match seeking.state {
+		State::Seeking { .. } => (),
+		_ => panic!("a new target should stop tracking"),
+	}
+}
@@ -1,8 +1,6 @@
 use std::f32::consts::{PI};
 
-use cgmath::{Vector3};
-
-use inverse_kinematics::{Chain};
+use inverse_kinematics::{Chain, Joint, JointKind, SolveResult, SolveStatus, Solver, Target};
 
 
 #[derive(Clone, PartialEq)]
@@ -10,20 +8,30 @@ pub enum State {
 	Seeking {
 		base_angles: Vec<f32>,
 		target_angles: Vec<f32>,
+		velocities: Vec<f32>, // radians per frame, carried between frames for the acceleration limits
 		frames_to_wait: u16,
 		num_transition_frames: u16,
 		current_frame: u16,
-		target: Vector3<f32>,
+		target: Target,
+		result: SolveResult,
 	},
 	Waiting {
-		target: Vector3<f32>,
+		target: Target,
 		frames_to_wait: u16,
+		result: SolveResult,
+	},
+	// re-solves every frame, warm-started from wherever the chain currently is, until given a NewTarget
+	Tracking {
+		target: Target,
+		velocities: Vec<f32>,
+		result: SolveResult,
 	},
 	Done,
 }
 
 pub enum Transition {
-	NewTarget { target: Vector3<f32>, num_transition_frames: u16 },
+	NewTarget { target: Target, num_transition_frames: u16 },
+	Track { target: Target }, // starts tracking or moves the tracked target, fine to send every frame
 	Update,
 }
 
@@ -31,28 +39,42 @@ pub fn update(chain: &Chain, transition: Transition) -> Chain {
 	match transition {
 		Transition::Update => match chain.state {
 			State::Done => chain.clone(),
-			State::Seeking { ref base_angles, ref target_angles, frames_to_wait, num_transition_frames, current_frame, target } => {
-				let current_frame = current_frame + 1;
+			State::Seeking { ref base_angles, ref target_angles, ref velocities, frames_to_wait, num_transition_frames, current_frame, target, ref result } => {
+				const EPSILON: f32 = 0.0001;
+
+				// keeps counting at the end of the curve while velocity limited joints catch up
+				//
+				let current_frame = (current_frame + 1).min(num_transition_frames);
+
+				let t = chain.easing.ease(current_frame as f32 / num_transition_frames as f32);
 
-				let t = (current_frame + 1) as f32 / num_transition_frames as f32;
+				let (mut angles, velocities): (Vec<f32>, Vec<f32>) = chain.joints.iter().zip(chain.angles.iter()).zip(velocities.iter())
+					.zip(base_angles.iter().zip(target_angles.iter())).map(
+					|(((joint, &angle), &velocity), (base, target))| limit_motion(joint, angle, velocity, base + t * (target - base), *target)
+				).unzip();
 
-				let angles = base_angles.iter().zip(target_angles.iter()).map(
-					|(base, target)| { base + t * (target - base) }
-				).collect();
+				let arrived = current_frame == num_transition_frames && angles.iter().zip(target_angles.iter()).all(
+					|(angle, target)| (angle - target).abs() < EPSILON
+				);
+
+				let state = if arrived {
+					angles = target_angles.to_vec();
 
-				let state = if current_frame == num_transition_frames {
 					State::Waiting {
 						target: target,
 						frames_to_wait: frames_to_wait,
+						result: result.clone(),
 					}
 				} else {
 					State::Seeking {
 						base_angles: base_angles.to_vec(),
 						target_angles: target_angles.to_vec(),
+						velocities: velocities,
 						frames_to_wait: frames_to_wait,
 						num_transition_frames: num_transition_frames,
 						current_frame: current_frame,
 						target: target,
+						result: result.clone(),
 					}
 				};
 
@@ -61,30 +83,40 @@ pub fn update(chain: &Chain, transition: Transition) -> Chain {
 					joints: chain.joints.to_vec(),
 					state: state,
 					position: chain.position,
-					ik_fun: chain.ik_fun,
+					solver: chain.solver,
+					easing: chain.easing,
 				}
 			},
-			State::Waiting { target, frames_to_wait } => {
+			State::Waiting { target, frames_to_wait, ref result } => {
 				Chain {
 					angles: chain.angles.to_vec(),
 					joints: chain.joints.to_vec(),
 					state:
 					if frames_to_wait == 0 { State::Done }
-					else {                   State::Waiting { target: target, frames_to_wait: frames_to_wait - 1 } }
+					else {                   State::Waiting { target: target, frames_to_wait: frames_to_wait - 1, result: result.clone() } }
 					,
 					position: chain.position,
-					ik_fun: chain.ik_fun,
+					solver: chain.solver,
+					easing: chain.easing,
 				}
 			},
+			State::Tracking { target, ref velocities, .. } => track(chain, target, velocities),
+		},
+		Transition::Track { target } => match chain.state {
+			State::Seeking { ref velocities, .. } | State::Tracking { ref velocities, .. } => track(chain, target, velocities),
+			_ => track(chain, target, &vec![0.0; chain.joints.len()]),
 		},
 		Transition::NewTarget{ target, num_transition_frames } => {
-			let mut target_angles = (chain.ik_fun)(&chain, target);
+			let result = solve(chain, target);
+
+			// no point lingering on a target we couldn't get to, move on as soon as we've settled
+			//
+			let frames_to_wait = match result.status {
+				SolveStatus::Converged => 60,
+				_                      => 0,
+			};
 
-			for (base, mut target) in chain.angles.iter().zip(target_angles.iter_mut()) {
-				const TWO_PI: f32 = 2.0 * PI;
-				let difference = *target - *base;
-				if difference.abs() > PI { *target -= TWO_PI * (difference / TWO_PI).round(); }
-			}
+			let target_angles = short_way_around(chain, &result.angles);
 
 			let chain = Chain {
 				joints: chain.joints.to_vec(),
@@ -92,15 +124,90 @@ pub fn update(chain: &Chain, transition: Transition) -> Chain {
 				state: State::Seeking {
 					base_angles: chain.angles.to_vec(),
 					target_angles: target_angles,
-					frames_to_wait: 60,
+					velocities: vec![0.0; chain.joints.len()],
+					frames_to_wait: frames_to_wait,
 					num_transition_frames: num_transition_frames,
 					current_frame: 0,
 					target: target,
+					result: result,
 				},
 				position: chain.position,
-				ik_fun: chain.ik_fun,
+				solver: chain.solver,
+				easing: chain.easing,
 			};
 			update(&chain, Transition::Update)
 		},
 	}
 }
+
+fn solve(chain: &Chain, target: Target) -> SolveResult {
+	match chain.solver.solve(chain, target) {
+		SolveResult { status: SolveStatus::Singular, .. } => Solver::DampedLeastSquares(Default::default()).solve(chain, target),
+		result => result,
+	}
+}
+
+// the solution can be a full turn away from where a revolute joint already is
+//
+fn short_way_around(chain: &Chain, angles: &[f32]) -> Vec<f32> {
+	const TWO_PI: f32 = 2.0 * PI;
+
+	chain.joints.iter().zip(chain.angles.iter()).zip(angles.iter()).map(|((joint, base), &target)| {
+		let difference = target - *base;
+		if joint.kind == JointKind::Revolute && difference.abs() > PI {
+			let wrapped = target - TWO_PI * (difference / TWO_PI).round();
+
+			// only take the short way around if it doesn't leave the joint's range
+			//
+			if joint.clamp(wrapped) == wrapped { return wrapped }
+		}
+		target
+	}).collect()
+}
+
+// the solver starts from the chain's current angles, so a target that moves a little each frame only needs a few iterations
+//
+fn track(chain: &Chain, target: Target, velocities: &[f32]) -> Chain {
+	let result = solve(chain, target);
+	let goal = short_way_around(chain, &result.angles);
+
+	let (angles, velocities) = chain.joints.iter().zip(chain.angles.iter()).zip(velocities.iter()).zip(goal.iter()).map(
+		|(((joint, &angle), &velocity), &goal)| limit_motion(joint, angle, velocity, goal, goal)
+	).unzip();
+
+	Chain {
+		angles: angles,
+		joints: chain.joints.to_vec(),
+		state: State::Tracking {
+			target: target,
+			velocities: velocities,
+			result: result,
+		},
+		position: chain.position,
+		solver: chain.solver,
+		easing: chain.easing,
+	}
+}
+
+// follows the eased curve as closely as the joint's velocity and acceleration limits allow,
+// braking in time to stop on the target rather than sailing past it
+//
+fn limit_motion(joint: &Joint, angle: f32, velocity: f32, desired: f32, target: f32) -> (f32, f32) {
+	let remaining = target - angle;
+
+	let mut step = (desired - angle)
+		.max(velocity - joint.max_acceleration).min(velocity + joint.max_acceleration)
+		.max(-joint.max_velocity).min(joint.max_velocity);
+
+	// fastest speed that can still shed max_acceleration a frame and stop within the remaining distance,
+	// i.e. the positive root of v^2 / 2a + v / 2 = remaining
+	//
+	if joint.max_acceleration.is_finite() && step * remaining > 0.0 {
+		let a = joint.max_acceleration;
+		let stopping = (-0.5 * a + (0.25 * a * a + 2.0 * a * remaining.abs()).sqrt()).min(remaining.abs());
+		step = step.max(-stopping).min(stopping);
+	}
+
+	let next = joint.clamp(angle + step);
+	(next, next - angle)
+}
@@ -17,6 +17,7 @@ mod model;
 mod unlit_model;
 mod scheduler;
 mod inverse_kinematics;
+mod scene;
 
 mod debug;
 
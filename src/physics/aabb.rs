@@ -0,0 +1,117 @@
+use cgmath::{InnerSpace, Point3, Vector3};
+
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Aabb {
+	pub min: Point3<f32>,
+	pub max: Point3<f32>,
+}
+
+impl Aabb {
+	pub fn from_points(points: &[Point3<f32>]) -> Aabb {
+		let first = Aabb { min: points[0], max: points[0] };
+
+		points[1..].iter().fold(first, |bounds, &point| bounds.union(&Aabb { min: point, max: point }))
+	}
+
+	pub fn union(&self, other: &Aabb) -> Aabb {
+		Aabb {
+			min: Point3::new(self.min.x.min(other.min.x), self.min.y.min(other.min.y), self.min.z.min(other.min.z)),
+			max: Point3::new(self.max.x.max(other.max.x), self.max.y.max(other.max.y), self.max.z.max(other.max.z)),
+		}
+	}
+
+	// grown by `amount` on every side
+	//
+	pub fn expand(&self, amount: f32) -> Aabb {
+		let amount = Vector3::new(amount, amount, amount);
+
+		Aabb { min: self.min - amount, max: self.max + amount }
+	}
+
+	// the box covering this one moved anywhere along `motion`
+	//
+	pub fn sweep(&self, motion: Vector3<f32>) -> Aabb {
+		self.union(&Aabb { min: self.min + motion, max: self.max + motion })
+	}
+
+	pub fn overlaps(&self, other: &Aabb) -> bool {
+		self.min.x <= other.max.x && other.min.x <= self.max.x &&
+		self.min.y <= other.max.y && other.min.y <= self.max.y &&
+		self.min.z <= other.max.z && other.min.z <= self.max.z
+	}
+
+	// how far along `direction` the ray from `origin` gets before it's inside, 0 if it starts
+	// inside and None if it misses or doesn't get there within `max_distance`
+	//
+	pub fn ray_entry(&self, origin: Point3<f32>, direction: Vector3<f32>, max_distance: f32) -> Option<f32> {
+		self.ray_hit(origin, direction, max_distance).map(|hit| hit.0)
+	}
+
+	// ray_entry and the outward normal of the side the ray comes in through, which is just
+	// back along the ray when it starts inside
+	//
+	pub fn ray_hit(&self, origin: Point3<f32>, direction: Vector3<f32>, max_distance: f32) -> Option<(f32, Vector3<f32>)> {
+		let mut near: f32 = 0.0;
+		let mut far = max_distance;
+		let mut normal = -direction.normalize();
+
+		for axis in 0..3 {
+			let (start, step) = (component(origin, axis), vector_component(direction, axis));
+			let (min, max) = (component(self.min, axis), component(self.max, axis));
+
+			if step == 0.0 {
+				if start < min || start > max { return None }
+				continue;
+			}
+			let (enter, exit) = ((min - start) / step, (max - start) / step);
+			let (enter, exit) = if enter <= exit { (enter, exit) } else { (exit, enter) };
+
+			if enter > near {
+				near = enter;
+				normal = axis_vector(axis) * -step.signum();
+			}
+			far = far.min(exit);
+			if near > far { return None }
+		}
+		Some((near, normal))
+	}
+
+	pub fn center(&self) -> Point3<f32> {
+		self.min + (self.max - self.min) * 0.5
+	}
+
+	// 0, 1 or 2 for x, y or z
+	//
+	pub fn longest_axis(&self) -> usize {
+		let extent = self.max - self.min;
+
+		if extent.x >= extent.y && extent.x >= extent.z { 0 }
+		else if extent.y >= extent.z                    { 1 }
+		else                                            { 2 }
+	}
+}
+
+pub fn component(point: Point3<f32>, axis: usize) -> f32 {
+	match axis {
+		0 => point.x,
+		1 => point.y,
+		_ => point.z,
+	}
+}
+
+fn axis_vector(axis: usize) -> Vector3<f32> {
+	match axis {
+		0 => Vector3::unit_x(),
+		1 => Vector3::unit_y(),
+		_ => Vector3::unit_z(),
+	}
+}
+
+fn vector_component(vector: Vector3<f32>, axis: usize) -> f32 {
+	match axis {
+		0 => vector.x,
+		1 => vector.y,
+		_ => vector.z,
+	}
+}
@@ -0,0 +1,207 @@
+use std::cmp::{Ordering};
+
+use cgmath::{Point3, Vector3};
+
+use physics::aabb::{Aabb, component};
+use physics::frustum::{Frustum};
+
+
+// a bounding volume hierarchy over anything with a box around it, split at the median centre
+// along the longest axis until a handful of entries are left in each leaf, frames never change
+// so instead of refitting as things move a new tree is built for every frame that needs one
+//
+#[derive(Clone)]
+pub struct AabbTree<T> {
+	// in leaf order
+	entries: Vec<(T, Aabb)>,
+	nodes:   Vec<Node>,
+}
+
+#[derive(Clone)]
+enum Node {
+	Leaf   { bounds: Aabb, first: usize, count: usize },
+	Branch { bounds: Aabb, left: usize, right: usize },
+}
+
+impl Node {
+	fn bounds(&self) -> &Aabb {
+		match *self {
+			Node::Leaf   { ref bounds, .. } => bounds,
+			Node::Branch { ref bounds, .. } => bounds,
+		}
+	}
+}
+
+const LEAF_SIZE: usize = 4;
+
+impl<T: Copy> AabbTree<T> {
+	pub fn new(entries: Vec<(T, Aabb)>) -> AabbTree<T> {
+		let mut entries = entries;
+		let mut nodes = Vec::new();
+
+		if !entries.is_empty() { build(&mut entries, 0, &mut nodes); }
+
+		AabbTree {
+			entries: entries,
+			nodes:   nodes,
+		}
+	}
+
+	// every entry whose bounds overlap `bounds`
+	//
+	pub fn overlapping(&self, bounds: &Aabb) -> Vec<T> {
+		self.search(|node| node.overlaps(bounds)).into_iter().map(|n| self.entries[n].0).collect()
+	}
+
+	// every entry at least partly inside the frustum
+	//
+	pub fn visible(&self, frustum: &Frustum) -> Vec<T> {
+		self.search(|node| frustum.intersects(node)).into_iter().map(|n| self.entries[n].0).collect()
+	}
+
+	// every entry whose bounds the ray passes through within `max_distance`, nearest first by
+	// where the ray enters them, `direction` needn't be normalized and distances are in its lengths
+	//
+	pub fn raycast(&self, origin: Point3<f32>, direction: Vector3<f32>, max_distance: f32) -> Vec<(T, f32)> {
+		let mut hits: Vec<(T, f32)> = self.search(|node| node.ray_entry(origin, direction, max_distance).is_some()).into_iter().map(|n| {
+			let (entry, bounds) = self.entries[n];
+			(entry, bounds.ray_entry(origin, direction, max_distance).unwrap())
+		}).collect();
+
+		hits.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
+		hits
+	}
+
+	// each pair of entries whose bounds overlap, once
+	//
+	pub fn pairs(&self) -> Vec<(T, T)> {
+		let mut pairs = Vec::new();
+
+		for (n, &(entry, ref bounds)) in self.entries.iter().enumerate() {
+			for m in self.search(|node| node.overlaps(bounds)) {
+				if m > n { pairs.push((entry, self.entries[m].0)) }
+			}
+		}
+		pairs
+	}
+
+	// indices into `entries` of every entry that passes `test`, which also has to pass for any
+	// box around it for the search to get that far
+	//
+	fn search<F: Fn(&Aabb) -> bool>(&self, test: F) -> Vec<usize> {
+		let mut found = Vec::new();
+		if self.nodes.is_empty() { return found }
+
+		let mut stack = vec![0];
+		while let Some(index) = stack.pop() {
+			let node = &self.nodes[index];
+			if !test(node.bounds()) { continue }
+
+			match *node {
+				Node::Leaf { first, count, .. } => {
+					for entry in first..first + count {
+						if test(&self.entries[entry].1) { found.push(entry) }
+					}
+				},
+				Node::Branch { left, right, .. } => {
+					stack.push(left);
+					stack.push(right);
+				},
+			}
+		}
+		found
+	}
+}
+
+// sorts `entries` (which start at `first` in the final list) into leaf order, returns the new node's index
+//
+fn build<T>(entries: &mut [(T, Aabb)], first: usize, nodes: &mut Vec<Node>) -> usize {
+	let bounds = entries[1..].iter().fold(entries[0].1, |bounds, entry| bounds.union(&entry.1));
+
+	if entries.len() <= LEAF_SIZE {
+		nodes.push(Node::Leaf { bounds: bounds, first: first, count: entries.len() });
+		return nodes.len() - 1;
+	}
+
+	let centres: Vec<_> = entries.iter().map(|entry| entry.1.center()).collect();
+	let axis = Aabb::from_points(&centres).longest_axis();
+	entries.sort_by(|a, b| {
+		component(a.1.center(), axis).partial_cmp(&component(b.1.center(), axis)).unwrap_or(Ordering::Equal)
+	});
+
+	let index = nodes.len();
+	nodes.push(Node::Leaf { bounds: bounds, first: first, count: 0 }); // replaced once the children exist
+
+	let middle = entries.len() / 2;
+	let (left_entries, right_entries) = entries.split_at_mut(middle);
+	let left  = build(left_entries,  first,          nodes);
+	let right = build(right_entries, first + middle, nodes);
+
+	nodes[index] = Node::Branch { bounds: bounds, left: left, right: right };
+	index
+}
+
+#[cfg(test)]
+mod tests {
+	use cgmath::{Point3, Vector3};
+
+	use physics::aabb::{Aabb};
+	use super::{AabbTree};
+
+
+	// boxes scattered around deterministically, some overlapping
+	//
+	fn scattered() -> AabbTree<usize> {
+		AabbTree::new((0..100).map(|n| {
+			let centre = Point3::new((n as f32 * 0.37).sin() * 20.0, (n % 5) as f32, (n as f32 * 0.23).cos() * 20.0);
+			(n, Aabb { min: centre, max: centre }.expand(0.5 + (n % 3) as f32 * 0.5))
+		}).collect())
+	}
+
+	fn linear<F: Fn(&Aabb) -> bool>(tree: &AabbTree<usize>, test: F) -> Vec<usize> {
+		let mut found: Vec<usize> = tree.entries.iter().filter(|entry| test(&entry.1)).map(|entry| entry.0).collect();
+		found.sort();
+		found
+	}
+
+	#[test]
+	fn finds_what_a_linear_search_finds() {
+		let tree = scattered();
+		let query = Aabb { min: Point3::new(-5.0, 1.0, -8.0), max: Point3::new(6.0, 4.0, 3.0) };
+
+		let mut found = tree.overlapping(&query);
+		found.sort();
+		let expected = linear(&tree, |bounds| bounds.overlaps(&query));
+		assert!(!expected.is_empty());
+		assert_eq!(found, expected);
+
+		let mut pairs: Vec<(usize, usize)> = tree.pairs().into_iter().map(|(a, b)| (a.min(b), a.max(b))).collect();
+		pairs.sort();
+		let mut expected = Vec::new();
+		for &(a, ref first) in &tree.entries {
+			for &(b, ref second) in &tree.entries {
+				if a < b && first.overlaps(second) { expected.push((a, b)) }
+			}
+		}
+		expected.sort();
+		assert!(!expected.is_empty());
+		assert_eq!(pairs, expected);
+	}
+
+	#[test]
+	fn rays_hit_the_nearest_box_first() {
+		let tree = scattered();
+		let origin = Point3::new(-30.0, 0.0, 20.0); // level with the first box
+		let direction = Vector3::new(1.0, 0.0, 0.0);
+
+		let hits = tree.raycast(origin, direction, 60.0);
+		let expected = linear(&tree, |bounds| bounds.ray_entry(origin, direction, 60.0).is_some());
+		let mut found: Vec<usize> = hits.iter().map(|hit| hit.0).collect();
+		found.sort();
+
+		assert!(!expected.is_empty());
+		assert_eq!(found, expected);
+		assert!(hits.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+		assert!(tree.raycast(origin, -direction, 60.0).is_empty());
+	}
+}
@@ -0,0 +1,136 @@
+use cgmath::{InnerSpace, Point3, Vector3};
+
+use physics::aabb::{Aabb};
+use physics::aabb_tree::{AabbTree};
+use physics::triangle::{Triangle};
+
+
+// the static triangles of the level in an AabbTree, found by index
+//
+pub struct Bvh {
+	pub triangles: Vec<Triangle>,
+	tree: AabbTree<usize>,
+}
+
+impl Bvh {
+	pub fn new(triangles: Vec<Triangle>) -> Bvh {
+		// slivers have no normal to push back along
+		//
+		let triangles: Vec<Triangle> = triangles.into_iter().filter(|triangle| triangle.cross().magnitude2() > 1e-12).collect();
+		let tree = AabbTree::new(triangles.iter().map(|triangle| triangle.bounds()).enumerate().collect());
+
+		Bvh {
+			triangles: triangles,
+			tree:      tree,
+		}
+	}
+
+	// indices into `triangles` of every triangle whose bounds overlap `bounds`
+	//
+	pub fn overlapping(&self, bounds: &Aabb) -> Vec<usize> {
+		self.tree.overlapping(bounds)
+	}
+
+	// the nearest triangle a ray with a normalized `direction` hits within `max_distance`, and
+	// its normal turned to face back along the ray
+	//
+	pub fn raycast(&self, origin: Point3<f32>, direction: Vector3<f32>, max_distance: f32) -> Option<(f32, Vector3<f32>)> {
+		let mut nearest: Option<(f32, usize)> = None;
+
+		for (index, entry) in self.tree.raycast(origin, direction, max_distance) {
+			if nearest.map_or(false, |(distance, _)| distance < entry) { break }
+
+			if let Some(distance) = self.triangles[index].ray_hit(origin, direction) {
+				if distance <= max_distance && nearest.map_or(true, |nearest| distance < nearest.0) { nearest = Some((distance, index)) }
+			}
+		}
+
+		nearest.map(|(distance, index)| {
+			let normal = self.triangles[index].cross().normalize();
+			(distance, if normal.dot(direction) > 0.0 { -normal } else { normal })
+		})
+	}
+}
+
+// the levels other modules' tests run in are built here too
+//
+#[cfg(test)]
+pub mod tests {
+	use cgmath::{InnerSpace, Point3, Vector3};
+
+	use physics::aabb::{Aabb};
+	use physics::triangle::{Triangle};
+	use super::{Bvh};
+
+
+	fn quad(a: Point3<f32>, b: Point3<f32>, c: Point3<f32>, d: Point3<f32>) -> Vec<Triangle> {
+		vec![Triangle::new(a, b, c), Triangle::new(a, c, d)]
+	}
+
+	fn floor_triangles() -> Vec<Triangle> {
+		quad(
+			Point3::new(-50.0, 0.0, -50.0), Point3::new(-50.0, 0.0, 50.0),
+			Point3::new( 50.0, 0.0,  50.0), Point3::new( 50.0, 0.0, -50.0)
+		)
+	}
+
+	// a floor at y = 0
+	//
+	pub fn floor() -> Bvh {
+		Bvh::new(floor_triangles())
+	}
+
+	// the floor with a wall facing -x at x = 5
+	//
+	pub fn room() -> Bvh {
+		let mut triangles = floor_triangles();
+		triangles.extend(quad(
+			Point3::new(5.0, -10.0, -50.0), Point3::new(5.0, 10.0, -50.0),
+			Point3::new(5.0,  10.0,  50.0), Point3::new(5.0, -10.0, 50.0)
+		));
+		Bvh::new(triangles)
+	}
+
+	#[test]
+	fn finds_what_a_linear_search_finds() {
+		// a strip of small triangles winding around, deterministic so failures reproduce
+		//
+		let triangles: Vec<Triangle> = (0..200).map(|n| {
+			let x = (n as f32 * 0.37).sin() * 20.0;
+			let z = (n as f32 * 0.23).cos() * 20.0;
+			let y = (n % 7) as f32;
+			Triangle::new(Point3::new(x, y, z), Point3::new(x + 1.0, y, z), Point3::new(x, y + 1.0, z + 0.5))
+		}).collect();
+		let bvh = Bvh::new(triangles);
+
+		let query = Aabb { min: Point3::new(-5.0, 1.0, -8.0), max: Point3::new(6.0, 4.0, 3.0) };
+
+		let mut found = bvh.overlapping(&query);
+		found.sort();
+		let expected: Vec<usize> = (0..bvh.triangles.len()).filter(|&n| bvh.triangles[n].bounds().overlaps(&query)).collect();
+
+		assert!(!expected.is_empty());
+		assert_eq!(found, expected);
+	}
+
+	#[test]
+	fn rays_hit_the_nearest_triangle_facing_back_at_them() {
+		let corner = |x, y, z| Point3::new(x, y, z);
+		let bvh = Bvh::new(vec![
+			Triangle::new(corner(-5.0, 0.0, -5.0), corner(-5.0, 0.0, 5.0), corner(5.0, 0.0, 0.0)),
+			Triangle::new(corner(-5.0, 2.0, -5.0), corner(-5.0, 2.0, 5.0), corner(5.0, 2.0, 0.0)),
+		]);
+		let down = Vector3::new(0.0, -1.0, 0.0);
+
+		let (distance, normal) = bvh.raycast(Point3::new(0.0, 10.0, 0.0), down, 20.0).unwrap();
+		assert!((distance - 8.0).abs() < 1e-4);
+		assert!((normal - Vector3::new(0.0, 1.0, 0.0)).magnitude() < 1e-4);
+
+		let (distance, normal) = bvh.raycast(Point3::new(0.0, -1.0, 0.0), -down, 20.0).unwrap();
+		assert!((distance - 1.0).abs() < 1e-4);
+		assert!((normal - Vector3::new(0.0, -1.0, 0.0)).magnitude() < 1e-4);
+
+		assert!(bvh.raycast(Point3::new(0.0, 10.0, 0.0), down, 5.0).is_none());
+		assert!(bvh.raycast(Point3::new(8.0, 10.0, 0.0), down, 20.0).is_none());
+	}
+}
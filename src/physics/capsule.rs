@@ -0,0 +1,237 @@
+use cgmath::{InnerSpace, Point3, Vector3};
+
+use physics::aabb::{Aabb};
+use physics::bvh::{Bvh};
+use physics::shape::{sphere_ray_hit};
+use physics::triangle::{Triangle};
+
+
+// kept between the capsule and whatever it rests against, so sliding along a surface
+// doesn't start every sweep already touching it
+//
+const SKIN: f32 = 0.01;
+
+// a corner takes two slides, anything more is jitter
+//
+const MAX_SLIDES: usize = 4;
+
+// an upright capsule standing on `position`, `height` includes both caps
+//
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Capsule {
+	pub radius: f32,
+	pub height: f32,
+}
+
+impl Capsule {
+	// the segment the capsule is swept around, bottom then top
+	//
+	fn segment(&self, position: Point3<f32>) -> (Point3<f32>, Point3<f32>) {
+		(	position + Vector3::new(0.0, self.radius, 0.0),
+			position + Vector3::new(0.0, self.height - self.radius, 0.0)
+		)
+	}
+
+	pub fn bounds(&self, position: Point3<f32>) -> Aabb {
+		let (bottom, top) = self.segment(position);
+
+		Aabb::from_points(&[bottom, top]).expand(self.radius)
+	}
+
+	// where a ray with a normalized `direction` first hits the outside, with the normal there
+	//
+	pub fn ray_hit(&self, position: Point3<f32>, origin: Point3<f32>, direction: Vector3<f32>) -> Option<(f32, Vector3<f32>)> {
+		let (bottom, top) = self.segment(position);
+		let mut hits = vec![
+			sphere_ray_hit(bottom, self.radius, origin, direction),
+			sphere_ray_hit(top,    self.radius, origin, direction),
+		];
+
+		// the side is upright so only x and z matter until the height is checked
+		//
+		let (x, z) = (origin.x - bottom.x, origin.z - bottom.z);
+		let a = direction.x * direction.x + direction.z * direction.z;
+		let b = x * direction.x + z * direction.z;
+		let c = x * x + z * z - self.radius * self.radius;
+		let discriminant = b * b - a * c;
+		if a > 1e-12 && discriminant >= 0.0 {
+			let distance = (-b - discriminant.sqrt()) / a;
+			let point = origin + direction * distance;
+
+			if distance >= 0.0 && point.y >= bottom.y && point.y <= top.y {
+				hits.push(Some((distance, Vector3::new(point.x - bottom.x, 0.0, point.z - bottom.z) / self.radius)));
+			}
+		}
+
+		hits.into_iter().filter_map(|hit| hit).fold(None, |nearest: Option<(f32, Vector3<f32>)>, hit| {
+			match nearest {
+				Some(nearest) if nearest.0 <= hit.0 => Some(nearest),
+				_                                   => Some(hit),
+			}
+		})
+	}
+
+	// how far the surface of the capsule is from the triangle (negative when overlapping)
+	// and the direction that pushes it away
+	//
+	fn contact(&self, position: Point3<f32>, triangle: &Triangle) -> (f32, Vector3<f32>) {
+		let (bottom, top) = self.segment(position);
+		let (on_segment, on_triangle) = triangle.closest_to_segment(bottom, top);
+
+		let offset = on_segment - on_triangle;
+		let distance = offset.magnitude();
+		if distance > 1e-6 { return (distance - self.radius, offset / distance) }
+
+		// the segment passes through the face, back out the side the middle of the capsule is on
+		//
+		let normal = triangle.cross().normalize();
+		let middle = bottom + (top - bottom) * 0.5;
+		let normal = if (middle - triangle.a).dot(normal) >= 0.0 { normal } else { -normal };
+
+		(-self.radius, normal)
+	}
+
+	// conservative advancement: the gap to a convex triangle shrinks no faster than the capsule
+	// moves and, along a straight line, only shrinks until the closest approach, so stepping by
+	// the gap can't tunnel and a contact we're leaving can be ignored
+	//
+	// the fraction of `motion` travelled before touching the triangle and the contact normal
+	//
+	fn time_of_impact(&self, position: Point3<f32>, motion: Vector3<f32>, triangle: &Triangle) -> Option<(f32, Vector3<f32>)> {
+		const MAX_ITERATIONS: usize = 32;
+		// below this the motion runs along the face, allowing for what earlier slides left behind
+		const GLANCING: f32 = 1e-4;
+
+		let length = motion.magnitude();
+
+		// only moving into the face can be blocked by it, otherwise sliding along a wall or floor
+		// catches on the edges between its triangles (the faces either side of a real corner still block)
+		//
+		let face = triangle.cross().normalize();
+		let face = if (self.segment(position).0 - triangle.a).dot(face) >= 0.0 { face } else { -face };
+		if motion.dot(face) >= -GLANCING * length { return None }
+
+		let mut t = 0.0;
+		for _ in 0..MAX_ITERATIONS {
+			let (gap, normal) = self.contact(position + motion * t, triangle);
+
+			if gap <= SKIN {
+				return if motion.dot(normal) < 0.0 { Some((t, normal)) } else { None };
+			}
+			t += (gap - SKIN * 0.5) / length;
+			if t > 1.0 { return None }
+		}
+		Some((t, self.contact(position + motion * t, triangle).1))
+	}
+
+	// the earliest contact along `motion` against `level`
+	//
+	pub fn sweep(&self, level: &Bvh, position: Point3<f32>, motion: Vector3<f32>) -> Option<(f32, Vector3<f32>)> {
+		if motion.magnitude2() == 0.0 { return None }
+
+		let swept = self.bounds(position).sweep(motion).expand(SKIN);
+
+		level.overlapping(&swept).into_iter()
+			.filter_map(|index| self.time_of_impact(position, motion, &level.triangles[index]))
+			.fold(None, |earliest, (t, normal)| match earliest {
+				Some((earliest_t, _)) if earliest_t <= t => earliest,
+				_                                        => Some((t, normal)),
+			})
+	}
+
+	// the normal of walkable ground within `reach` below the capsule
+	//
+	pub fn ground(&self, level: &Bvh, position: Point3<f32>, reach: f32) -> Option<Vector3<f32>> {
+		const MAX_SLOPE_COSINE: f32 = 0.64; // about 50 degrees, anything steeper is slid down
+
+		match self.sweep(level, position, Vector3::new(0.0, -reach, 0.0)) {
+			Some((_, normal)) if normal.y >= MAX_SLOPE_COSINE => Some(normal),
+			_                                                 => None,
+		}
+	}
+
+	// pushes the capsule out of anything it's sunk into, by the deepest overlap along each contact
+	//
+	pub fn depenetrate(&self, level: &Bvh, position: Point3<f32>) -> Point3<f32> {
+		let mut position = position;
+
+		for _ in 0..MAX_SLIDES {
+			let nearby = level.overlapping(&self.bounds(position).expand(SKIN));
+			let deepest = nearby.into_iter()
+				.map(|index| self.contact(position, &level.triangles[index]))
+				.filter(|&(gap, _)| gap < 0.0)
+				.fold(None, |deepest: Option<(f32, Vector3<f32>)>, (gap, normal)| match deepest {
+					Some((deepest_gap, _)) if deepest_gap <= gap => deepest,
+					_                                            => Some((gap, normal)),
+				});
+
+			match deepest {
+				Some((gap, normal)) => position = position + normal * (SKIN - gap),
+				None                => break,
+			}
+		}
+		position
+	}
+
+	// moves as far along `motion` as the level allows, then spends what's left sliding along
+	// whatever was hit, returns where the capsule ends up
+	//
+	pub fn slide(&self, level: &Bvh, position: Point3<f32>, motion: Vector3<f32>) -> Point3<f32> {
+		let mut position = self.depenetrate(level, position);
+		let mut remaining = motion;
+
+		for _ in 0..MAX_SLIDES {
+			if remaining.magnitude2() < 1e-12 { break }
+
+			match self.sweep(level, position, remaining) {
+				Some((t, normal)) => {
+					position = position + remaining * t;
+					let rest = remaining * (1.0 - t);
+					remaining = rest - normal * rest.dot(normal);
+				},
+				None => {
+					position = position + remaining;
+					break;
+				},
+			}
+		}
+		position
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use cgmath::{InnerSpace, Point3, Vector3};
+
+	use physics::bvh::tests::{room};
+	use super::{Capsule};
+
+
+	const CAPSULE: Capsule = Capsule { radius: 1.0, height: 4.0 };
+
+	#[test]
+	fn stops_on_the_floor() {
+		let end = CAPSULE.slide(&room(), Point3::new(0.0, 3.0, 0.0), Vector3::new(0.0, -10.0, 0.0));
+
+		assert!(end.y >= 0.0 && end.y < 0.05, "ended at {:?}", end);
+	}
+
+	#[test]
+	fn slides_along_a_wall_without_passing_it() {
+		let level = room();
+		let start = Point3::new(0.0, 1.0, 0.0);
+
+		// far enough in one step to skip the wall entirely without a sweep
+		//
+		let end = CAPSULE.slide(&level, start, Vector3::new(20.0, 0.0, 10.0));
+
+		assert!(end.x <= 4.0 && end.x > 3.9, "ended at {:?}", end);
+		assert!((end.z - 10.0).abs() < 1e-3, "ended at {:?}", end);
+		assert!((end.y - 1.0).abs() < 1e-3, "ended at {:?}", end);
+
+		// walking along the floor isn't blocked by it
+		//
+		let along = CAPSULE.slide(&level, Point3::new(0.0, 0.005, 0.0), Vector3::new(-3.0, 0.0, 0.0));
+		assert!((along - Point3::new(-3.0, 0.005, 0.0)).magnitude() < 1e-3, "ended at {:?}", along);
+	}
+}
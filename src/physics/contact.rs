@@ -0,0 +1,208 @@
+use std::f32::{INFINITY, NEG_INFINITY};
+
+use cgmath::{EuclideanSpace, InnerSpace, Point3, Vector3};
+
+use physics::aabb_tree::{AabbTree};
+use physics::bvh::{Bvh};
+use physics::rigid_body::{RigidBody};
+use physics::shape::{Hull, Shape};
+
+
+// a point where body a touches body b (or the level), found at the start of a step
+//
+#[derive(Copy, Clone, Debug)]
+pub struct Contact {
+	pub a:      usize,
+	pub b:      Option<usize>, // None is the level
+	pub point:  Point3<f32>,
+	pub normal: Vector3<f32>, // out of b, into a
+	pub depth:  f32,
+}
+
+// contacts only need to start this close for the solver to hold bodies apart
+//
+const MARGIN: f32 = 0.02;
+
+// corners of a hull that have gone through the level, hull faces clipped against each other
+// and spheres against whatever is closest, edge on edge hits between hulls only show up
+// through the nearest face
+//
+pub fn find_contacts(bodies: &[RigidBody], level: &Bvh) -> Vec<Contact> {
+	let mut contacts = Vec::new();
+
+	for (a, body) in bodies.iter().enumerate() {
+		if body.inverse_mass == 0.0 { continue }
+
+		for (point, normal, depth) in level_contacts(body, level) {
+			contacts.push(Contact { a: a, b: None, point: point, normal: normal, depth: depth });
+		}
+	}
+
+	let tree = AabbTree::new(bodies.iter().map(|body| body.bounds().expand(MARGIN)).enumerate().collect());
+	for (a, b) in tree.pairs() {
+		// either way round works, the lower index first keeps a and b predictable
+		//
+		let (a, b) = (a.min(b), a.max(b));
+		let (first, second) = (&bodies[a], &bodies[b]);
+		if first.inverse_mass == 0.0 && second.inverse_mass == 0.0 { continue }
+
+		for (point, normal, depth) in pair_contacts(first, second) {
+			contacts.push(Contact { a: a, b: Some(b), point: point, normal: normal, depth: depth });
+		}
+	}
+	contacts
+}
+
+fn level_contacts(body: &RigidBody, level: &Bvh) -> Vec<(Point3<f32>, Vector3<f32>, f32)> {
+	let nearby = level.overlapping(&body.bounds().expand(MARGIN));
+	let mut contacts: Vec<(Point3<f32>, Vector3<f32>, f32)> = Vec::new();
+
+	{
+		// neighbouring triangles of a flat floor both see a corner over their shared edge
+		//
+		let mut add = |contact: (Point3<f32>, Vector3<f32>, f32)| {
+			let duplicate = contacts.iter().any(|&(point, normal, _)| {
+				(point - contact.0).magnitude2() < 1e-6 && normal.dot(contact.1) > 0.999
+			});
+			if !duplicate { contacts.push(contact) }
+		};
+
+		match body.shape {
+			Shape::Sphere(radius) => {
+				for &index in &nearby {
+					let closest = level.triangles[index].closest_point(body.position);
+					let offset = body.position - closest;
+					let distance = offset.magnitude();
+
+					if distance < radius + MARGIN && distance > 1e-6 {
+						add((closest, offset / distance, radius - distance));
+					}
+				}
+			},
+			Shape::Hull(ref hull) => {
+				for &vertex in &hull.vertices {
+					let corner = body.position + body.orientation * vertex;
+
+					for &index in &nearby {
+						let triangle = &level.triangles[index];
+
+						// level triangles are one sided as far as we know, the body's centre says which side it's on
+						//
+						let normal = triangle.cross().normalize();
+						let normal = if (body.position - triangle.a).dot(normal) >= 0.0 { normal } else { -normal };
+
+						let height = (corner - triangle.a).dot(normal);
+						if height > MARGIN { continue }
+
+						let projected = corner - normal * height;
+						if (triangle.closest_point(projected) - projected).magnitude2() > 1e-8 { continue }
+
+						add((corner, normal, -height));
+					}
+				}
+			},
+		}
+	}
+	contacts
+}
+
+fn pair_contacts(a: &RigidBody, b: &RigidBody) -> Vec<(Point3<f32>, Vector3<f32>, f32)> {
+	match (&a.shape, &b.shape) {
+		(&Shape::Sphere(radius_a), &Shape::Sphere(radius_b)) => {
+			let offset = a.position - b.position;
+			let distance = offset.magnitude();
+			if distance >= radius_a + radius_b + MARGIN || distance < 1e-6 { return Vec::new() }
+
+			let normal = offset / distance;
+			vec![(b.position + normal * radius_b, normal, radius_a + radius_b - distance)]
+		},
+		(&Shape::Sphere(radius), &Shape::Hull(ref hull)) => sphere_hull(a.position, radius, b, hull),
+		(&Shape::Hull(ref hull), &Shape::Sphere(radius)) => {
+			sphere_hull(b.position, radius, a, hull).into_iter().map(|(point, normal, depth)| (point, -normal, depth)).collect()
+		},
+		(&Shape::Hull(ref hull_a), &Shape::Hull(ref hull_b)) => hull_hull(a, hull_a, b, hull_b),
+	}
+}
+
+// the closest face stands in for the closest point, which overestimates a little near edges
+// and corners, the normal points from the hull to the sphere
+//
+fn sphere_hull(centre: Point3<f32>, radius: f32, body: &RigidBody, hull: &Hull) -> Vec<(Point3<f32>, Vector3<f32>, f32)> {
+	let local = body.orientation.conjugate() * (centre - body.position);
+	let (separation, face) = hull.separation(local);
+	if separation >= radius + MARGIN { return Vec::new() }
+
+	let normal = body.orientation * face.normal;
+	vec![(centre - normal * radius, normal, radius - separation)]
+}
+
+// the face of either hull the other is least far through is the reference, the face of the
+// other hull facing it most squarely is clipped to its sides and whatever is left below the
+// reference face touches, so boxes resting face on face get all four corners of the overlap
+//
+fn hull_hull(a: &RigidBody, hull_a: &Hull, b: &RigidBody, hull_b: &Hull) -> Vec<(Point3<f32>, Vector3<f32>, f32)> {
+	let (separation_a, face_a) = deepest_face(a, hull_a, b, hull_b);
+	let (separation_b, face_b) = deepest_face(b, hull_b, a, hull_a);
+	if separation_a.max(separation_b) > MARGIN { return Vec::new() }
+
+	// a small bias to b keeps the same reference face from step to step when they're close
+	//
+	if separation_b + 1e-3 >= separation_a {
+		clip(b, hull_b, face_b, a, hull_a)
+	} else {
+		clip(a, hull_a, face_a, b, hull_b).into_iter().map(|(point, normal, depth)| (point, -normal, depth)).collect()
+	}
+}
+
+// how far the rest of the other hull is outside each of the reference hull's faces, the
+// face with the least overlap wins
+//
+fn deepest_face(reference: &RigidBody, hull: &Hull, other: &RigidBody, other_hull: &Hull) -> (f32, usize) {
+	let corners: Vec<_> = other_hull.vertices.iter().map(|&vertex| other.position + other.orientation * vertex).collect();
+
+	(0..hull.faces.len()).map(|n| {
+		let normal = reference.orientation * hull.faces[n].normal;
+		let offset = hull.faces[n].offset + normal.dot(reference.position.to_vec());
+		let separation = corners.iter().fold(INFINITY, |least, &corner| least.min(normal.dot(corner.to_vec()) - offset));
+
+		(separation, n)
+	}).fold((NEG_INFINITY, 0), |best, candidate| if candidate.0 > best.0 { candidate } else { best })
+}
+
+// contacts on the incident hull's face, with normals out of the reference face
+//
+fn clip(reference: &RigidBody, reference_hull: &Hull, face: usize, incident: &RigidBody, hull: &Hull) -> Vec<(Point3<f32>, Vector3<f32>, f32)> {
+	let face = &reference_hull.faces[face];
+	let world = |body: &RigidBody, vertex: Vector3<f32>| body.position + body.orientation * vertex;
+	let normal = reference.orientation * face.normal;
+
+	let incident_face = hull.faces.iter().fold(&hull.faces[0], |best, candidate| {
+		if (incident.orientation * candidate.normal).dot(normal) < (incident.orientation * best.normal).dot(normal) { candidate } else { best }
+	});
+	let mut polygon: Vec<Point3<f32>> = incident_face.vertices.iter().map(|&n| world(incident, hull.vertices[n])).collect();
+
+	let corners: Vec<Point3<f32>> = face.vertices.iter().map(|&n| world(reference, reference_hull.vertices[n])).collect();
+	for n in 0..corners.len() {
+		let (from, to) = (corners[n], corners[(n + 1) % corners.len()]);
+		let side = (to - from).cross(normal);
+		let outside = |point: Point3<f32>| (point - from).dot(side);
+
+		let mut clipped = Vec::new();
+		for m in 0..polygon.len() {
+			let (current, next) = (polygon[m], polygon[(m + 1) % polygon.len()]);
+			let (d_current, d_next) = (outside(current), outside(next));
+
+			if d_current <= 0.0 { clipped.push(current) }
+			if (d_current <= 0.0) != (d_next <= 0.0) {
+				clipped.push(current + (next - current) * (d_current / (d_current - d_next)));
+			}
+		}
+		polygon = clipped;
+	}
+
+	let plane = corners[0];
+	polygon.into_iter().filter_map(|point| {
+		let depth = (plane - point).dot(normal);
+		if depth > -MARGIN { Some((point, normal, depth)) } else { None }
+	}).collect()
+}
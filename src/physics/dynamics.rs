@@ -0,0 +1,205 @@
+use cgmath::{InnerSpace, Vector3};
+
+use physics::bvh::{Bvh};
+use physics::contact::{Contact, find_contacts};
+use physics::rigid_body::{RigidBody};
+
+
+// sequential impulses, more passes settle stacks better
+//
+const ITERATIONS: usize = 10;
+
+// the fraction of any overlap beyond SLOP pushed out per step
+//
+const BAUMGARTE: f32 = 0.2;
+const SLOP:      f32 = 0.01;
+
+// slower impacts don't bounce, so resting contacts stay at rest instead of buzzing
+//
+const RESTITUTION_THRESHOLD: f32 = 1.0;
+
+// one fixed step of every body, gravity first, then impulses so nothing moves into what it's
+// touching, then the move itself
+//
+pub fn step(bodies: &[RigidBody], level: &Bvh, dt: f32) -> Vec<RigidBody> {
+	let mut bodies: Vec<RigidBody> = bodies.iter().map(|body| body.accelerate(dt)).collect();
+
+	let contacts = find_contacts(&bodies, level);
+	solve(&mut bodies, &contacts, dt);
+
+	bodies.iter().map(|body| body.integrate(dt)).collect()
+}
+
+// what each contact needs through every iteration, worked out once
+//
+struct Constraint {
+	contact:         Contact,
+	offset_a:        Vector3<f32>,
+	offset_b:        Vector3<f32>,
+	tangents:        [Vector3<f32>; 2],
+	normal_mass:     f32,
+	tangent_masses:  [f32; 2],
+	target_velocity: f32, // how fast the bodies should separate along the normal
+	friction:        f32,
+	normal_impulse:  f32, // accumulated, never pulls bodies together
+	tangent_impulse: [f32; 2],
+}
+
+fn solve(bodies: &mut [RigidBody], contacts: &[Contact], dt: f32) {
+	let mut constraints: Vec<Constraint> = {
+		let bodies: &[RigidBody] = bodies;
+
+		contacts.iter().map(|&contact| {
+			let a = &bodies[contact.a];
+			let b = contact.b.map(|b| &bodies[b]);
+
+			let offset_a = contact.point - a.position;
+			let offset_b = b.map_or(Vector3::new(0.0, 0.0, 0.0), |b| contact.point - b.position);
+
+			let mass = |direction: Vector3<f32>| {
+				let angular = |body: &RigidBody, offset: Vector3<f32>| {
+					(body.inverse_inertia_world() * offset.cross(direction)).cross(offset).dot(direction)
+				};
+				let inverse
+					= a.inverse_mass + angular(a, offset_a)
+					+ b.map_or(0.0, |b| b.inverse_mass + angular(b, offset_b));
+
+				if inverse > 0.0 { 1.0 / inverse } else { 0.0 }
+			};
+
+			let normal = contact.normal;
+			let tangent = if normal.x.abs() < 0.9 { Vector3::unit_x() } else { Vector3::unit_y() };
+			let tangent = tangent.cross(normal).normalize();
+			let tangents = [tangent, normal.cross(tangent)];
+
+			let approach = relative_velocity(bodies, &contact, offset_a, offset_b).dot(normal);
+			let (restitution, friction) = match b {
+				Some(b) => (a.restitution.max(b.restitution), (a.friction * b.friction).sqrt()),
+				None    => (a.restitution, a.friction),
+			};
+
+			// separated contacts let the bodies close the gap this step but no more
+			//
+			let target_velocity = if contact.depth < 0.0 {
+				contact.depth / dt
+			} else {
+				let bounce = if approach < -RESTITUTION_THRESHOLD { -restitution * approach } else { 0.0 };
+				bounce.max(BAUMGARTE * (contact.depth - SLOP).max(0.0) / dt)
+			};
+
+			Constraint {
+				contact:         contact,
+				offset_a:        offset_a,
+				offset_b:        offset_b,
+				tangents:        tangents,
+				normal_mass:     mass(normal),
+				tangent_masses:  [mass(tangents[0]), mass(tangents[1])],
+				target_velocity: target_velocity,
+				friction:        friction,
+				normal_impulse:  0.0,
+				tangent_impulse: [0.0, 0.0],
+			}
+		}).collect()
+	};
+
+	for _ in 0..ITERATIONS {
+		for constraint in &mut constraints {
+			let contact = constraint.contact;
+
+			let velocity = relative_velocity(bodies, &contact, constraint.offset_a, constraint.offset_b);
+			let change = (constraint.target_velocity - velocity.dot(contact.normal)) * constraint.normal_mass;
+			let total = (constraint.normal_impulse + change).max(0.0);
+			let applied = total - constraint.normal_impulse;
+			constraint.normal_impulse = total;
+			apply(bodies, &contact, contact.normal * applied, constraint.offset_a, constraint.offset_b);
+
+			// friction can't push harder than the contact is pressed together
+			//
+			let limit = constraint.friction * constraint.normal_impulse;
+			for n in 0..2 {
+				let tangent = constraint.tangents[n];
+				let velocity = relative_velocity(bodies, &contact, constraint.offset_a, constraint.offset_b);
+				let change = -velocity.dot(tangent) * constraint.tangent_masses[n];
+				let total = (constraint.tangent_impulse[n] + change).max(-limit).min(limit);
+				let applied = total - constraint.tangent_impulse[n];
+				constraint.tangent_impulse[n] = total;
+				apply(bodies, &contact, tangent * applied, constraint.offset_a, constraint.offset_b);
+			}
+		}
+	}
+}
+
+// a's velocity at the contact relative to b's
+//
+fn relative_velocity(bodies: &[RigidBody], contact: &Contact, offset_a: Vector3<f32>, offset_b: Vector3<f32>) -> Vector3<f32> {
+	let velocity_b = contact.b.map_or(Vector3::new(0.0, 0.0, 0.0), |b| bodies[b].velocity_at(offset_b));
+
+	bodies[contact.a].velocity_at(offset_a) - velocity_b
+}
+
+fn apply(bodies: &mut [RigidBody], contact: &Contact, impulse: Vector3<f32>, offset_a: Vector3<f32>, offset_b: Vector3<f32>) {
+	bodies[contact.a].apply_impulse(impulse, offset_a);
+	if let Some(b) = contact.b { bodies[b].apply_impulse(-impulse, offset_b) }
+}
+
+#[cfg(test)]
+mod tests {
+	use cgmath::{InnerSpace, Point3, Vector3};
+
+	use physics::bvh::{Bvh};
+	use physics::bvh::tests::{floor};
+	use physics::rigid_body::{RigidBody};
+	use physics::shape::{Shape};
+	use super::{step};
+
+
+	const DT: f32 = 1.0 / 120.0;
+
+	fn run(bodies: Vec<RigidBody>, level: &Bvh, steps: usize) -> Vec<RigidBody> {
+		(0..steps).fold(bodies, |bodies, _| step(&bodies, level, DT))
+	}
+
+	#[test]
+	fn boxes_come_to_rest_and_stack() {
+		let level = floor();
+		let cube = Shape::cuboid(Vector3::new(1.0, 1.0, 1.0));
+		let bodies = vec![
+			RigidBody::new(cube.clone(), Point3::new(0.0, 1.5, 0.0), 1.0),
+			RigidBody::new(cube.clone(), Point3::new(0.2, 3.8, 0.1), 1.0),
+		];
+
+		let settled = run(bodies, &level, 480);
+
+		for (body, height) in settled.iter().zip([1.0, 3.0].iter()) {
+			assert!((body.position.y - height).abs() < 0.05, "resting at {:?}", body.position);
+			assert!(body.velocity.magnitude() < 0.05, "still moving at {:?}", body.velocity);
+			assert!(body.angular_velocity.magnitude() < 0.05, "still turning at {:?}", body.angular_velocity);
+		}
+	}
+
+	#[test]
+	fn balls_bounce_lower_each_time_and_push_each_other() {
+		let level = floor();
+		let ball = RigidBody { restitution: 0.5, .. RigidBody::new(Shape::sphere(0.5), Point3::new(0.0, 5.0, 0.0), 1.0) };
+
+		let mut bodies = vec![ball];
+		let mut bounced = false;
+		let mut highest_after_bounce: f32 = 0.0;
+		for _ in 0..240 {
+			bodies = step(&bodies, &level, DT);
+			if bodies[0].velocity.y > 0.0 { bounced = true }
+			if bounced { highest_after_bounce = highest_after_bounce.max(bodies[0].position.y) }
+		}
+		assert!(bounced);
+		assert!(highest_after_bounce > 1.0 && highest_after_bounce < 5.0, "bounced to {}", highest_after_bounce);
+
+		// a ball rolling into a resting one hands over its momentum along the line between them
+		//
+		let moving = RigidBody { velocity: Vector3::new(5.0, 0.0, 0.0), .. RigidBody::new(Shape::sphere(0.5), Point3::new(-2.0, 0.5, 0.0), 1.0) };
+		let resting = RigidBody::new(Shape::sphere(0.5), Point3::new(0.0, 0.5, 0.0), 1.0);
+
+		let after = run(vec![moving, resting], &level, 120);
+		assert!(after[1].position.x > 0.5, "pushed to {:?}", after[1].position);
+		assert!(after[0].position.x < after[1].position.x - 0.9);
+	}
+}
@@ -0,0 +1,67 @@
+use cgmath::{Matrix, Matrix4, Vector4};
+
+use physics::aabb::{Aabb};
+
+
+// the six planes bounding what a view projection puts on screen, each as (a, b, c, d) with
+// ax + by + cz + d >= 0 inside
+//
+#[derive(Copy, Clone, Debug)]
+pub struct Frustum {
+	pub planes: [Vector4<f32>; 6],
+}
+
+impl Frustum {
+	// clip space is -w..w on every axis, so each plane is the last row plus or minus another
+	//
+	pub fn from_matrix(view_projection: Matrix4<f32>) -> Frustum {
+		let (x, y, z, w) = (view_projection.row(0), view_projection.row(1), view_projection.row(2), view_projection.row(3));
+
+		Frustum {
+			planes: [w + x, w - x, w + y, w - y, w + z, w - z],
+		}
+	}
+
+	// conservative, boxes near a corner outside every plane only at different places get through
+	//
+	pub fn intersects(&self, bounds: &Aabb) -> bool {
+		self.planes.iter().all(|plane| {
+			// the corner furthest along the plane's normal
+			//
+			let x = if plane.x >= 0.0 { bounds.max.x } else { bounds.min.x };
+			let y = if plane.y >= 0.0 { bounds.max.y } else { bounds.min.y };
+			let z = if plane.z >= 0.0 { bounds.max.z } else { bounds.min.z };
+
+			plane.x * x + plane.y * y + plane.z * z + plane.w >= 0.0
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use cgmath::{Matrix4, Point3, Vector4};
+
+	use physics::aabb::{Aabb};
+	use super::{Frustum};
+
+
+	#[test]
+	fn keeps_boxes_that_reach_inside() {
+		// squashes -10..10 on every axis into clip space
+		//
+		let scale = Matrix4::from_cols(
+			Vector4::new(0.1, 0.0, 0.0, 0.0),
+			Vector4::new(0.0, 0.1, 0.0, 0.0),
+			Vector4::new(0.0, 0.0, 0.1, 0.0),
+			Vector4::new(0.0, 0.0, 0.0, 1.0)
+		);
+		let frustum = Frustum::from_matrix(scale);
+		let around = |x, y, z| Aabb { min: Point3::new(x, y, z), max: Point3::new(x, y, z) }.expand(1.0);
+
+		assert!(frustum.intersects(&around(0.0, 0.0, 0.0)));
+		assert!(frustum.intersects(&around(10.5, 0.0, 0.0)));
+		assert!(frustum.intersects(&around(0.0, -9.0, 10.5)));
+		assert!(!frustum.intersects(&around(12.0, 0.0, 0.0)));
+		assert!(!frustum.intersects(&around(0.0, 0.0, -11.5)));
+	}
+}
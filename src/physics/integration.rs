@@ -0,0 +1,24 @@
+use cgmath::{InnerSpace, Point3, Vector3};
+
+
+// units per second per second, shared by the player and rigid bodies
+//
+pub const GRAVITY: f32 = 30.0;
+
+// semi-implicit Euler, the velocity first and then the position with the new velocity,
+// stable at a fixed dt where explicit Euler slowly gains energy
+//
+pub fn integrate(position: Point3<f32>, velocity: Vector3<f32>, acceleration: Vector3<f32>, dt: f32) -> (Point3<f32>, Vector3<f32>) {
+	let velocity = velocity + acceleration * dt;
+
+	(position + velocity * dt, velocity)
+}
+
+// `current` moved towards `target` by at most `max_change`
+//
+pub fn approach(current: Vector3<f32>, target: Vector3<f32>, max_change: f32) -> Vector3<f32> {
+	let difference = target - current;
+	let distance = difference.magnitude();
+
+	if distance <= max_change { target } else { current + difference * (max_change / distance) }
+}
@@ -1,5 +1,22 @@
+pub use self::aabb::{Aabb};
+pub use self::frustum::{Frustum};
+pub use self::objects::{ObjectId, RayHit};
 pub use self::physics_context::{PhysicsContext};
-pub use self::physics_frame::{PhysicsFrame};
+pub use self::physics_frame::{PhysicsFrame, DEFAULT_SEED};
+pub use self::rigid_body::{RigidBody};
 
 mod physics_context;
 mod physics_frame;
+mod aabb;
+mod aabb_tree;
+mod bvh;
+mod capsule;
+mod contact;
+mod dynamics;
+mod frustum;
+mod integration;
+mod objects;
+mod player;
+mod rigid_body;
+mod shape;
+mod triangle;
@@ -0,0 +1,91 @@
+use cgmath::{EuclideanSpace, InnerSpace, Matrix4, Point3, SquareMatrix, Vector3, Vector4};
+
+use inverse_kinematics::{Chain, Skeleton, BONE_HALF_WIDTH};
+use physics::aabb::{Aabb};
+
+
+// names the things in the world for queries against the scene, indices are into the lists
+// the frame or scene keeps them in
+//
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum ObjectId {
+	Level,
+	Tree(usize),
+	Player,
+	Chain(usize),
+	Skeleton(usize),
+	Body(usize),
+}
+
+// the first thing a ray hits, with the normal of its surface there
+//
+#[derive(Copy, Clone, Debug)]
+pub struct RayHit {
+	pub object:   ObjectId,
+	pub point:    Point3<f32>,
+	pub normal:   Vector3<f32>,
+	pub distance: f32,
+}
+
+// the bones are drawn as boxes around their joints, this covers them and the gnomons
+//
+const BONE_RADIUS: f32 = 1.0;
+
+pub fn chain_bounds(chain: &Chain) -> Aabb {
+	joint_bounds(Point3::from_vec(chain.position), &chain.cumulative_transforms())
+}
+
+pub fn skeleton_bounds(skeleton: &Skeleton) -> Aabb {
+	joint_bounds(Point3::from_vec(skeleton.position), &skeleton.cumulative_transforms())
+}
+
+// rays hit the boxes the bones are drawn as
+//
+pub fn chain_ray_hit(chain: &Chain, origin: Point3<f32>, direction: Vector3<f32>, max_distance: f32) -> Option<(f32, Vector3<f32>)> {
+	let lengths = chain.joints.iter().map(|joint| joint.length()).filter(|&length| length != 0.0);
+
+	bones_ray_hit(chain.position, lengths.zip(chain.visible_joint_transforms()), origin, direction, max_distance)
+}
+
+pub fn skeleton_ray_hit(skeleton: &Skeleton, origin: Point3<f32>, direction: Vector3<f32>, max_distance: f32) -> Option<(f32, Vector3<f32>)> {
+	let lengths = skeleton.bones.iter().map(|bone| bone.joint.length()).filter(|&length| length != 0.0);
+
+	bones_ray_hit(skeleton.position, lengths.zip(skeleton.visible_joint_transforms()), origin, direction, max_distance)
+}
+
+// each bone's transform is rigid, so the ray is taken into the bone's frame and checked
+// against a box there, distances come out the same
+//
+fn bones_ray_hit<I>(base: Vector3<f32>, bones: I, origin: Point3<f32>, direction: Vector3<f32>, max_distance: f32) -> Option<(f32, Vector3<f32>)>
+	where I: Iterator<Item = (f32, Matrix4<f32>)>
+{
+	let mut nearest: Option<(f32, Vector3<f32>)> = None;
+
+	for (length, transform) in bones {
+		let transform = Matrix4::from_translation(base) * transform;
+		let to_bone = match transform.invert() {
+			Some(inverse) => inverse,
+			None          => continue,
+		};
+		let local_origin = Point3::from_homogeneous(to_bone * origin.to_homogeneous());
+		let local_direction = (to_bone * direction.extend(0.0)).truncate();
+
+		let bone = Aabb {
+			min: Point3::new(-BONE_HALF_WIDTH, 0.0,    -BONE_HALF_WIDTH),
+			max: Point3::new( BONE_HALF_WIDTH, length,  BONE_HALF_WIDTH),
+		};
+		if let Some((distance, normal)) = bone.ray_hit(local_origin, local_direction, max_distance) {
+			if nearest.map_or(true, |nearest| distance < nearest.0) {
+				nearest = Some((distance, (transform * normal.extend(0.0)).truncate().normalize()));
+			}
+		}
+	}
+	nearest
+}
+
+fn joint_bounds(base: Point3<f32>, transforms: &[Matrix4<f32>]) -> Aabb {
+	let mut points = vec![base];
+	points.extend(transforms.iter().map(|transform| base + (transform * Vector4::unit_w()).truncate()));
+
+	Aabb::from_points(&points).expand(BONE_RADIUS)
+}
@@ -1,7 +1,87 @@
-pub struct PhysicsContext;
+use std::collections::{HashMap};
+use std::path::{Path};
+
+use cgmath::{Matrix4, Point3, SquareMatrix};
+use tobj;
+
+use physics::aabb::{Aabb};
+use physics::aabb_tree::{AabbTree};
+use physics::bvh::{Bvh};
+use physics::objects::{ObjectId};
+use physics::triangle::{Triangle};
+use scene::{LEVEL_PATH, TREE_PATH, tree_transforms};
+
+
+// what physics needs that doesn't change from frame to frame
+//
+pub struct PhysicsContext {
+	// the level and trees, the player collides against these
+	pub level:   Bvh,
+	// the level and each tree as a whole, for scene queries
+	pub objects: AabbTree<ObjectId>,
+	// the same again with their own triangles, so a ray can tell which one it hit
+	pub meshes:  HashMap<ObjectId, Bvh>,
+}
 
 impl PhysicsContext {
-	pub fn new() -> PhysicsContext { PhysicsContext }
+	pub fn new() -> Result<PhysicsContext, String> {
+		let mut statics = vec![(ObjectId::Level, try!(load_triangles(LEVEL_PATH, Matrix4::identity())))];
+		for (index, transform) in tree_transforms().into_iter().enumerate() {
+			statics.push((ObjectId::Tree(index), try!(load_triangles(TREE_PATH, transform))));
+		}
+		Ok(PhysicsContext::from_meshes(statics))
+	}
+
+	// nothing to collide with, for when the data files aren't there (e.g. headless on a clean checkout)
+	//
+	pub fn empty() -> PhysicsContext {
+		PhysicsContext::from_meshes(Vec::new())
+	}
+
+	fn from_meshes(statics: Vec<(ObjectId, Vec<Triangle>)>) -> PhysicsContext {
+		let mut triangles = Vec::new();
+		let mut objects = Vec::new();
+		let mut meshes = HashMap::new();
+		for (object, mesh) in statics {
+			// an empty mesh has nothing for a query to find
+			//
+			if let Some(bounds) = bounds(&mesh) {
+				objects.push((object, bounds));
+				triangles.extend(mesh.iter().cloned());
+				meshes.insert(object, Bvh::new(mesh));
+			}
+		}
+
+		PhysicsContext {
+			level:   Bvh::new(triangles),
+			objects: AabbTree::new(objects),
+			meshes:  meshes,
+		}
+	}
+}
+
+// the same mesh Model::new draws, kept on the CPU
+//
+fn load_triangles(path: &str, transform: Matrix4<f32>) -> Result<Vec<Triangle>, String> {
+	let error_message = format!("Unable to load collision mesh({})", path);
+
+	let (mut models, _) = try!(tobj::load_obj(&Path::new(path)).map_err(|error| format!("{}: {:?}", error_message, error)));
+	let model: tobj::Model = try!(models.pop().ok_or(error_message));
+
+	let positions: Vec<Point3<f32>> = model.mesh.positions.chunks(3).map(|v| {
+		let transformed = transform * Point3::new(v[0], v[1], v[2]).to_homogeneous();
+		Point3::from_homogeneous(transformed)
+	}).collect();
+
+	Ok(model.mesh.indices.chunks(3).map(|tri| {
+		Triangle::new(positions[tri[0] as usize], positions[tri[1] as usize], positions[tri[2] as usize])
+	}).collect())
+}
+
+fn bounds(triangles: &[Triangle]) -> Option<Aabb> {
+	triangles.split_first().map(|(first, rest)| {
+		rest.iter().fold(first.bounds(), |bounds, triangle| bounds.union(&triangle.bounds()))
+	})
 }
 
 unsafe impl Send for PhysicsContext {}
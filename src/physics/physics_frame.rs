@@ -1,40 +1,94 @@
+use std::cmp::{Ordering};
+use std::io;
+use std::io::{Write};
 use std::sync::{Arc};
-use std::f32::consts::{PI};
+use std::f32::{INFINITY, NEG_INFINITY};
+use std::f32::consts::{PI, FRAC_PI_2};
 
-use cgmath::{Point3, Vector3, InnerSpace};
-use rand;
+use cgmath::{EuclideanSpace, Point3, Quaternion, Vector3, InnerSpace};
+use rand::{SeedableRng, StdRng};
 use rand::distributions::{IndependentSample, Range};
 
-use camera::{Camera, to_view_direction};
-use context::{Context};
+use camera::{Camera, FAR_PLANE, to_view_direction};
 use input::{InputFrame};
-use inverse_kinematics::{Axis, Chain, Joint, State, Transition, updater, cyclic_coordinate_descent, jacobian_transpose, jacobian_pseudo_inverse};
+use inverse_kinematics::{Axis, Bone, Chain, DampedLeastSquaresParameters, Easing, Effector, Joint, Skeleton, SkeletonSolver, Solver, State, Target, Transition, updater};
+use physics::aabb::{Aabb};
+use physics::aabb_tree::{AabbTree};
+use physics::dynamics;
+use physics::frustum::{Frustum};
+use physics::objects::{ObjectId, RayHit, chain_bounds, chain_ray_hit, skeleton_bounds, skeleton_ray_hit};
+use physics::physics_context::{PhysicsContext};
+use physics::player;
+use physics::player::{Player};
+use physics::rigid_body::{RigidBody};
+use physics::shape::{Shape};
 
 
 // TODO: put in a soft cap on elevation with a slow drift
 //
+#[derive(Clone)]
 pub struct PhysicsFrame {
 	pub frame_counter:   u64,
+	// fixed steps taken so far, there can be none or several per frame
+	pub step_counter:    u64,
+	// seconds per step
+	pub dt:              f32,
+	// input time not yet simulated, always less than dt
+	pub accumulator:     f32,
+	// the pose before the latest step, None until the first step
+	pub previous:        Option<Pose>,
 	pub camera:          Camera,
-	pub player_position: Point3<f32>,
+	pub player:          Player,
+	// a jump pressed since the last step, dropped if the player isn't on the ground for it
+	pub jump_pending:    bool,
 	pub ik_chains:       Vec<Chain>,
+	pub skeletons:       Vec<Skeleton>,
+	pub bodies:          Vec<RigidBody>,
+	// everything above that moves, as of the latest step, the rest is in PhysicsContext::objects
+	pub objects:         AabbTree<ObjectId>,
 
 	pub light_direction: Vector3<f32>,
 	pub aspect_ratio:    f32,
+
+	// every random choice comes from here, so a seed and an input sequence replay the same simulation
+	pub rng:             StdRng,
+}
+
+pub const DEFAULT_SEED: usize = 0;
+
+// the solvers' velocity limits and transition lengths are tuned per step, so this stays put
+// when the input or render rate changes
+//
+pub const PHYSICS_FREQUENCY: u64 = 120;
+
+// what moves from one step to the next, kept so rendering can blend between the last two steps
+//
+#[derive(Clone)]
+pub struct Pose {
+	pub player_position: Point3<f32>,
+	pub chain_angles:    Vec<Vec<f32>>,
+	pub skeleton_angles: Vec<Vec<f32>>,
+	pub bodies:          Vec<(Point3<f32>, Quaternion<f32>)>,
 }
 
 impl PhysicsFrame {
-	pub fn frame_zero(aspect_ratio: f32) -> PhysicsFrame {
+	pub fn frame_zero(aspect_ratio: f32, seed: usize) -> PhysicsFrame {
+		let seed: &[_] = &[seed];
+		let mut rng: StdRng = SeedableRng::from_seed(seed);
+
 		let light_direction = Vector3::new(0.4, -1.0, -0.6).normalize();
-		let player_position = Point3::new(0f32, 1f32, 0f32);
+		let player_position = player::SPAWN;
 		let camera = Camera::new(player_position, 0.0, 0.0, aspect_ratio);
 		let ik_chains = {
+			const MAX_VELOCITY:     f32 = 0.03;
+			const MAX_ACCELERATION: f32 = 0.001;
+
 			let joints = vec![
-				Joint { length: 0.0, axis: Axis::Y },
-				Joint { length: 3.0, axis: Axis::X },
-				Joint { length: 3.0, axis: Axis::X },
-				Joint { length: 3.0, axis: Axis::X }
-			];
+				Joint::revolute(Axis::Y.to_vector3(), 0.0),
+				Joint::revolute(Axis::X.to_vector3(), 3.0).with_limits(-FRAC_PI_2, FRAC_PI_2),
+				Joint::revolute(Axis::X.to_vector3(), 3.0).with_limits(-0.75 * PI, 0.75 * PI),
+				Joint::revolute(Axis::X.to_vector3(), 3.0).with_limits(-0.75 * PI, 0.75 * PI)
+			].into_iter().map(|joint| joint.with_velocity_limits(MAX_VELOCITY, MAX_ACCELERATION)).collect::<Vec<_>>();
 			let angles = vec![0.0, 0.0, 0.0, 0.1];
 			let state = State::Done;
 			vec![
@@ -43,92 +97,566 @@ impl PhysicsFrame {
 					angles: angles.clone(),
 					state: state.clone(),
 					position: Vector3::new(59.0, -9.0, -9.0),
-					ik_fun: cyclic_coordinate_descent,
+					solver: Solver::CyclicCoordinateDescent(Default::default()),
+					easing: Easing::EaseInOut,
 				},
 				Chain {
 					joints: joints.clone(),
 					angles: angles.clone(),
 					state: state.clone(),
 					position: Vector3::new(59.0, -9.0, 9.0),
-					ik_fun: jacobian_transpose,
+					solver: Solver::JacobianTranspose(Default::default()),
+					easing: Easing::CubicHermite { start_tangent: 0.0, end_tangent: 0.0 },
 				},
 				Chain {
 					joints: joints.clone(),
 					angles: angles.clone(),
 					state: state.clone(),
 					position: Vector3::new(59.0 + (PI/3.0).tan() * 9.0, -9.0, 0.0),
-					ik_fun: jacobian_pseudo_inverse,
-				}
+					solver: Solver::JacobianPseudoInverse(Default::default()),
+					easing: Easing::CriticallyDampedSpring { omega: 6.0 },
+				},
+				Chain {
+					joints: joints.clone(),
+					angles: angles.clone(),
+					state: state.clone(),
+					position: Vector3::new(59.0 + (PI/3.0).tan() * 9.0, -9.0, 18.0),
+					solver: Solver::DampedLeastSquares(Default::default()),
+					easing: Easing::EaseInOut,
+				},
+				Chain {
+					joints: joints.clone(),
+					angles: angles.clone(),
+					state: state.clone(),
+					position: Vector3::new(59.0 + (PI/3.0).tan() * 9.0, -9.0, -18.0),
+					solver: Solver::ForwardAndBackwardReaching(Default::default()),
+					easing: Easing::Linear,
+				},
+				tracker(joints.clone(), angles.clone(), Vector3::new(59.0, -9.0, 27.0), player_position),
 			]
 		};
 
-		PhysicsFrame {
+		let skeletons = vec![torso(Vector3::new(59.0, -9.0, -36.0), &mut rng)];
+
+		let mut frame = PhysicsFrame {
 			frame_counter:   0,
+			step_counter:    0,
+			dt:              1.0 / (PHYSICS_FREQUENCY as f32),
+			accumulator:     0.0,
+			previous:        None,
 			camera:          camera,
-			player_position: player_position,
+			player:          Player::new(player_position),
+			jump_pending:    false,
 			ik_chains:       ik_chains,
+			skeletons:       skeletons,
+			bodies:          bodies(),
+			objects:         AabbTree::new(Vec::new()),
 			light_direction: light_direction,
 			aspect_ratio:    aspect_ratio,
-		}
+			rng:             rng,
+		};
+		frame.objects = frame.moving_objects();
+		frame
 	}
 
-	pub fn new(context: Arc<Context>, frame: Arc<PhysicsFrame>, input_frame: Arc<InputFrame>) -> PhysicsFrame {
-		let angles_delta = -input_frame.view_angles_delta; // TODO: scale
-		let camera = frame.camera.update(frame.player_position, angles_delta.x, angles_delta.y, context.render.aspect_ratio());
+	pub fn new(physics: &PhysicsContext, frame: Arc<PhysicsFrame>, input_frame: Arc<InputFrame>) -> PhysicsFrame {
+		let aspect_ratio = match input_frame.resized {
+			Some((width, height)) if width > 0 && height > 0 => (width as f32) / (height as f32),
+			_                                                => frame.aspect_ratio, // minimizing gives 0 x 0
+		};
+
+		// looking isn't simulated, the camera turns once per input frame however many steps it takes
+		//
+		let angles_delta = -input_frame.view_angles_delta; // already radians, scaled by MouseSettings
+		let camera = frame.camera.update(frame.player.position, angles_delta.x, angles_delta.y, aspect_ratio);
 
-		let player_position = {
-			let view_direction = camera.view_direction();
+		let mut next = PhysicsFrame {
+			frame_counter: frame.frame_counter + 1,
+			accumulator:   frame.accumulator + input_frame.elapsed,
+			jump_pending:  frame.jump_pending || input_frame.jump,
+			camera:        camera,
+			aspect_ratio:  aspect_ratio,
+			.. (*frame).clone()
+		};
+
+		// what's under the crosshair, in the middle of the screen, becomes a chain's next target
+		//
+		if input_frame.pick {
+			let (eye, view_direction) = (next.camera.eye, next.camera.view_direction());
+			next.pick(physics, eye, view_direction);
+		}
+
+		while next.accumulator >= next.dt {
+			next.previous = Some(next.pose());
+			next.step(physics, &input_frame);
+			next.accumulator -= next.dt;
+		}
+		next.camera = next.camera.update(next.player.position, 0.0, 0.0, aspect_ratio);
+		next.objects = next.moving_objects();
+		next
+	}
+
+	fn step(&mut self, physics: &PhysicsContext, input_frame: &InputFrame) {
+		let wish_velocity = {
+			let view_direction = self.camera.view_direction();
 			let right = view_direction.cross(Vector3::new(0f32, 1f32, 0f32)).normalize();
 
-			let input_direction = input_frame.movement_delta; // TODO: scale
+			let input_direction = input_frame.movement_delta;
 
 			let flat_view_direction = (Vector3 { y: 0f32, .. view_direction }).normalize();
 			let flat_right          = (Vector3 { y: 0f32, ..          right }).normalize();
 
-			// TODO: generalize and factor out all integration
-			//
-			const FUDGE: f32 = 0.1f32;
-			let acceleration
-				= flat_view_direction * input_direction.x * FUDGE
-				+ flat_right          * input_direction.y * FUDGE;
-
-			frame.player_position + acceleration
+			(flat_view_direction * input_direction.x + flat_right * input_direction.y) * player::SPEED
 		};
-		let target = sphere_point(9.0);
-		let ik_chains = frame.ik_chains.iter().map(|chain| {
-			if chain.state == State::Done {
-				updater::update(chain, Transition::NewTarget{
+		self.player = self.player.step(&physics.level, wish_velocity, self.jump_pending, self.dt);
+		self.jump_pending = false;
+
+		let player_position = self.player.position;
+		let step_counter = self.step_counter;
+		let mut rng = self.rng;
+
+		let target = Target::from_position(sphere_point(&mut rng, 9.0));
+		self.ik_chains = self.ik_chains.iter().map(|chain| {
+			match chain.state {
+				State::Done => updater::update(chain, Transition::NewTarget{
 					target: target,
 					num_transition_frames: 180
-				})
-			} else {
-				updater::update(chain, Transition::Update)
+				}),
+				State::Tracking { .. } => updater::update(chain, Transition::Track{
+					target: player_target(chain, player_position)
+				}),
+				_ => updater::update(chain, Transition::Update),
 			}
 		}).collect();
+		self.skeletons = self.skeletons.iter().map(|skeleton| update_skeleton(skeleton, step_counter, &mut rng)).collect();
+		self.bodies = dynamics::step(&self.bodies, &physics.level, self.dt);
 
-		PhysicsFrame {
-			frame_counter: frame.frame_counter + 1,
-			camera: camera,
-			player_position: player_position,
-			ik_chains: ik_chains,
+		self.rng = rng;
+		self.step_counter += 1;
+	}
+
+	// the nearest chain not busy following the player reaches for whatever the ray hits first
+	//
+	fn pick(&mut self, physics: &PhysicsContext, origin: Point3<f32>, direction: Vector3<f32>) {
+		let hit = match self.raycast(physics, origin, direction, FAR_PLANE, |object| object != ObjectId::Player) {
+			Some(hit) => hit,
+			None      => return,
+		};
+
+		let distance = |chain: &Chain| (hit.point.to_vec() - chain.position).magnitude();
+		let nearest = self.ik_chains.iter().enumerate().filter(|&(_, chain)| {
+			match chain.state { State::Tracking { .. } => false, _ => true }
+		}).fold(None, |nearest: Option<(usize, f32)>, (index, chain)| {
+			match nearest {
+				Some(nearest) if nearest.1 <= distance(chain) => Some(nearest),
+				_                                             => Some((index, distance(chain))),
+			}
+		});
+
+		if let Some((index, _)) = nearest {
+			let retargeted = {
+				let chain = &self.ik_chains[index];
+
+				updater::update(chain, Transition::NewTarget {
+					target: Target::from_position(hit.point.to_vec() - chain.position),
+					num_transition_frames: 180
+				})
+			};
+			self.ik_chains[index] = retargeted;
+		}
+	}
+
+	pub fn pose(&self) -> Pose {
+		Pose {
+			player_position: self.player.position,
+			chain_angles:    self.ik_chains.iter().map(|chain| chain.angles.clone()).collect(),
+			skeleton_angles: self.skeletons.iter().map(|skeleton| skeleton.angles.clone()).collect(),
+			bodies:          self.bodies.iter().map(|body| (body.position, body.orientation)).collect(),
+		}
+	}
+
+	// how far from the previous step to the latest one to draw, `since` seconds after this frame
+	// was made, rendering runs a step behind so it can blend instead of guessing ahead
+	//
+	pub fn alpha(&self, since: f32) -> f32 {
+		((self.accumulator + since.max(0.0)) / self.dt).min(1.0)
+	}
+
+	// the frame as it would be `alpha` steps after the previous step, blended from the two latest
+	// steps so motion stays smooth when render frames don't line up with steps
+	//
+	pub fn interpolated(&self, alpha: f32) -> PhysicsFrame {
+		let previous = match self.previous {
+			Some(ref previous) => previous,
+			None               => return self.clone(),
+		};
+		let lerp = |from: f32, to: f32| from + (to - from) * alpha;
+		let lerp_angles = |from: &Vec<f32>, to: &Vec<f32>| -> Vec<f32> {
+			from.iter().zip(to.iter()).map(|(&from, &to)| lerp(from, to)).collect()
+		};
+
+		let mut frame = self.clone();
+		frame.player.position = previous.player_position + (self.player.position - previous.player_position) * alpha;
+		for (chain, from) in frame.ik_chains.iter_mut().zip(previous.chain_angles.iter()) {
+			chain.angles = lerp_angles(from, &chain.angles);
+		}
+		for (skeleton, from) in frame.skeletons.iter_mut().zip(previous.skeleton_angles.iter()) {
+			skeleton.angles = lerp_angles(from, &skeleton.angles);
+		}
+		for (body, &(position, orientation)) in frame.bodies.iter_mut().zip(previous.bodies.iter()) {
+			// q and -q are the same turn, blending towards the nearer one takes the short way round
+			//
+			let from = if orientation.dot(body.orientation) < 0.0 { -orientation } else { orientation };
+
+			body.position = position + (body.position - position) * alpha;
+			body.orientation = (from + (body.orientation - from) * alpha).normalize();
+		}
+		frame.camera = frame.camera.update(frame.player.position, 0.0, 0.0, frame.aspect_ratio);
+		frame.objects = frame.moving_objects();
+		frame
+	}
+
+	fn moving_objects(&self) -> AabbTree<ObjectId> {
+		let mut objects = vec![(ObjectId::Player, player::SHAPE.bounds(self.player.position))];
+
+		objects.extend(self.ik_chains.iter().enumerate().map(|(index, chain)| (ObjectId::Chain(index), chain_bounds(chain))));
+		objects.extend(self.skeletons.iter().enumerate().map(|(index, skeleton)| (ObjectId::Skeleton(index), skeleton_bounds(skeleton))));
+		objects.extend(self.bodies.iter().enumerate().map(|(index, body)| (ObjectId::Body(index), body.bounds())));
+
+		AabbTree::new(objects)
+	}
+}
+
+// scene queries, over the static objects in the context and the moving ones in the frame
+//
+impl PhysicsFrame {
+	pub fn overlapping(&self, physics: &PhysicsContext, bounds: &Aabb) -> Vec<ObjectId> {
+		let mut found = physics.objects.overlapping(bounds);
+		found.extend(self.objects.overlapping(bounds));
+		found
+	}
+
+	pub fn visible(&self, physics: &PhysicsContext, frustum: &Frustum) -> Vec<ObjectId> {
+		let mut found = physics.objects.visible(frustum);
+		found.extend(self.objects.visible(frustum));
+		found
+	}
+
+	// the first object the ray hits out of those `include` lets through, `direction` is normalized here
+	//
+	pub fn raycast<F>(&self, physics: &PhysicsContext, origin: Point3<f32>, direction: Vector3<f32>, max_distance: f32, include: F) -> Option<RayHit>
+		where F: Fn(ObjectId) -> bool
+	{
+		let direction = direction.normalize();
+		let mut nearest: Option<RayHit> = None;
+
+		for (object, entry) in self.raycast_bounds(physics, origin, direction, max_distance) {
+			if nearest.map_or(false, |hit| hit.distance < entry) { break }
+			if !include(object) { continue }
+
+			let hit = match object {
+				ObjectId::Level | ObjectId::Tree(_) => physics.meshes.get(&object).and_then(|mesh| mesh.raycast(origin, direction, max_distance)),
+				ObjectId::Player                    => player::SHAPE.ray_hit(self.player.position, origin, direction),
+				ObjectId::Chain(index)              => chain_ray_hit(&self.ik_chains[index], origin, direction, max_distance),
+				ObjectId::Skeleton(index)           => skeleton_ray_hit(&self.skeletons[index], origin, direction, max_distance),
+				ObjectId::Body(index)               => {
+					let body = &self.bodies[index];
+					body.shape.ray_hit(body.position, body.orientation, origin, direction)
+				},
+			};
+
+			if let Some((distance, normal)) = hit {
+				if distance <= max_distance && nearest.map_or(true, |hit| distance < hit.distance) {
+					nearest = Some(RayHit { object: object, point: origin + direction * distance, normal: normal, distance: distance });
+				}
+			}
+		}
+		nearest
+	}
 
-			light_direction: frame.light_direction,
-			aspect_ratio: frame.aspect_ratio,
+	// objects whose bounds the ray passes through, nearest first by where it enters them
+	//
+	pub fn raycast_bounds(&self, physics: &PhysicsContext, origin: Point3<f32>, direction: Vector3<f32>, max_distance: f32) -> Vec<(ObjectId, f32)> {
+		let mut found = physics.objects.raycast(origin, direction, max_distance);
+		found.extend(self.objects.raycast(origin, direction, max_distance));
+		found.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
+		found
+	}
+}
+
+impl PhysicsFrame {
+	// one line per frame, written by headless runs so two simulations can be diffed
+	//
+	pub fn write_state<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+		try!(write!(writer, "{} player {} {} {} camera {} {}",
+			self.frame_counter,
+			self.player.position.x, self.player.position.y, self.player.position.z,
+			self.camera.azimuth, self.camera.elevation
+		));
+		for chain in &self.ik_chains {
+			try!(write!(writer, " chain"));
+			for angle in &chain.angles { try!(write!(writer, " {}", angle)) }
+		}
+		for skeleton in &self.skeletons {
+			try!(write!(writer, " skeleton"));
+			for angle in &skeleton.angles { try!(write!(writer, " {}", angle)) }
 		}
+		for body in &self.bodies {
+			let (p, q) = (body.position, body.orientation);
+			try!(write!(writer, " body {} {} {} {} {} {} {}", p.x, p.y, p.z, q.s, q.v.x, q.v.y, q.v.z));
+		}
+		writeln!(writer, "")
 	}
 }
 
-fn sphere_point(radius: f32) -> Vector3<f32> {
-	let mut rng = rand::thread_rng();
+// reaches for the player, a few warm-started iterations a frame are plenty to keep up
+//
+fn tracker(joints: Vec<Joint>, angles: Vec<f32>, position: Vector3<f32>, player_position: Point3<f32>) -> Chain {
+	let chain = Chain {
+		joints: joints,
+		angles: angles,
+		state: State::Done,
+		position: position,
+		solver: Solver::DampedLeastSquares(DampedLeastSquaresParameters { max_iterations: 5, .. Default::default() }),
+		easing: Easing::Linear,
+	};
+	let target = player_target(&chain, player_position);
+
+	updater::update(&chain, Transition::Track{ target: target })
+}
+
+// targets are relative to the chain's base
+//
+fn player_target(chain: &Chain, player_position: Point3<f32>) -> Target {
+	Target::from_position(player_position.to_vec() - chain.position)
+}
+
+const HAND_EFFECTORS: [usize; 2] = [0, 1];
+const SHOULDER_HEIGHT: f32 = 3.0;
+
+// a spine with two arms and a head hanging off its top, the head effector just tries to stay upright
+//
+fn torso(position: Vector3<f32>, rng: &mut StdRng) -> Skeleton {
+	let bone = |length, axis: Axis, min_angle, max_angle, parent| Bone {
+		joint:  Joint::revolute(axis.to_vector3(), length).with_limits(min_angle, max_angle),
+		parent: parent,
+	};
+	let bones = vec![
+		bone(0.0, Axis::Y, NEG_INFINITY, INFINITY,  None),    // 0 hips
+		bone(3.0, Axis::X, -0.25 * PI,   0.25 * PI, Some(0)), // 1 spine
+		bone(0.0, Axis::Z, 0.0,          PI,        Some(1)), // 2 left shoulder
+		bone(3.0, Axis::X, -PI,          PI,        Some(2)), // 3 left upper arm
+		bone(3.0, Axis::X, 0.0,          0.75 * PI, Some(3)), // 4 left forearm
+		bone(0.0, Axis::Z, -PI,          0.0,       Some(1)), // 5 right shoulder
+		bone(3.0, Axis::X, -PI,          PI,        Some(5)), // 6 right upper arm
+		bone(3.0, Axis::X, 0.0,          0.75 * PI, Some(6)), // 7 right forearm
+		bone(3.0, Axis::X, -0.25 * PI,   0.25 * PI, Some(1)), // 8 neck
+	];
+	let angles = vec![0.0, 0.0, FRAC_PI_2, 0.0, 0.1, -FRAC_PI_2, 0.0, 0.1, 0.0];
+
+	let up = Vector3::new(0.0, SHOULDER_HEIGHT, 0.0);
+	let effectors = vec![
+		Effector { bone: 4, target: Target::from_position(up + sphere_point(rng, 6.0)), weight: 1.0 },
+		Effector { bone: 7, target: Target::from_position(up + sphere_point(rng, 6.0)), weight: 1.0 },
+		Effector { bone: 8, target: Target::from_position(up * 2.0),                    weight: 0.5 },
+	];
+
+	// a single heavily damped step per frame eases the skeleton towards its targets
+	//
+	let solver = SkeletonSolver::DampedLeastSquares(DampedLeastSquaresParameters {
+		max_iterations:  1,
+		initial_damping: 4.0,
+		.. Default::default()
+	});
+
+	Skeleton::new(bones, angles, effectors, position, solver)
+}
+
+// a stack of boxes to knock over, a ball dropped beside it and a crystal tumbling off to the side
+//
+fn bodies() -> Vec<RigidBody> {
+	let cube = Shape::cuboid(Vector3::new(1.0, 1.0, 1.0));
+	let crystal = Shape::hull(&[
+		Vector3::new(0.0, 2.0, 0.0), Vector3::new(0.0, -1.5, 0.0),
+		Vector3::new(1.0, 0.0, 0.0), Vector3::new(-0.5, 0.2, 0.87), Vector3::new(-0.5, -0.2, -0.87),
+		Vector3::new(0.6, 0.8, 0.5), Vector3::new(-0.7, 0.6, -0.2),
+	]);
+
+	vec![
+		RigidBody::new(cube.clone(), Point3::new(-10.0, 1.0, 0.0),   1.0),
+		RigidBody::new(cube.clone(), Point3::new(-10.0, 3.2, 0.3),   1.0),
+		RigidBody::new(cube.clone(), Point3::new(-10.2, 5.4, -0.2),  1.0),
+		RigidBody { restitution: 0.6, .. RigidBody::new(Shape::sphere(1.0), Point3::new(-10.0, 8.0, 4.0), 1.0) },
+		RigidBody { angular_velocity: Vector3::new(1.0, 0.5, 2.0), .. RigidBody::new(crystal, Point3::new(-14.0, 6.0, -4.0), 2.0) },
+	]
+}
+
+fn update_skeleton(skeleton: &Skeleton, step_counter: u64, rng: &mut StdRng) -> Skeleton {
+	const RETARGET_STEPS: u64 = 240;
+
+	let mut skeleton = skeleton.clone();
+
+	if step_counter % RETARGET_STEPS == 0 {
+		for &effector in &HAND_EFFECTORS {
+			skeleton.effectors[effector].target = Target::from_position(Vector3::new(0.0, SHOULDER_HEIGHT, 0.0) + sphere_point(rng, 6.0));
+		}
+	}
+	skeleton.angles = skeleton.solver.solve(&skeleton).angles;
+	skeleton
+}
 
+fn sphere_point(rng: &mut StdRng, radius: f32) -> Vector3<f32> {
 	let azimuth_range = Range::new(0.0, PI);
 	let elevation_range = Range::new(PI * 0.5, PI * 0.75);
 	let radius_range = Range::new(radius * 0.2, radius);
 
 	let unit = to_view_direction(
-		azimuth_range.ind_sample(&mut rng),
-		elevation_range.ind_sample(&mut rng)
+		azimuth_range.ind_sample(rng),
+		elevation_range.ind_sample(rng)
 	);
 
-	unit * radius_range.ind_sample(&mut rng)
+	unit * radius_range.ind_sample(rng)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::{Arc};
+
+	use cgmath::{InnerSpace, Vector2, Vector3};
+
+	use input::{InputFrame};
+	use inverse_kinematics::{State};
+	use physics::aabb::{Aabb};
+	use physics::objects::{ObjectId};
+	use physics::physics_context::{PhysicsContext};
+	use super::{PhysicsFrame, Pose};
+
+
+	fn run(seed: usize, frames: usize) -> Vec<u8> {
+		let input = Arc::new(InputFrame { elapsed: 1.0 / 120.0, .. InputFrame::frame_zero() });
+		let mut frame = Arc::new(PhysicsFrame::frame_zero(16.0 / 9.0, seed));
+		let physics = PhysicsContext::empty();
+		let mut dump = Vec::new();
+
+		for _ in 0..frames {
+			frame = Arc::new(PhysicsFrame::new(&physics, frame, input.clone()));
+			frame.write_state(&mut dump).unwrap();
+		}
+		dump
+	}
+
+	fn walk(input_frequency: usize, seconds: usize) -> PhysicsFrame {
+		let mut input = InputFrame { elapsed: 1.0 / (input_frequency as f32), .. InputFrame::frame_zero() };
+		input.movement_delta = Vector2::new(1.0, 0.5);
+		let input = Arc::new(input);
+
+		let physics = PhysicsContext::empty();
+		let mut frame = Arc::new(PhysicsFrame::frame_zero(16.0 / 9.0, 3));
+		for _ in 0..input_frequency * seconds {
+			frame = Arc::new(PhysicsFrame::new(&physics, frame, input.clone()));
+		}
+		(*frame).clone()
+	}
+
+	#[test]
+	fn same_seed_same_simulation() {
+		assert!(run(3, 300) == run(3, 300));
+		assert!(run(3, 300) != run(4, 300));
+	}
+
+	#[test]
+	fn input_rate_does_not_change_the_simulation() {
+		let fast = walk(240, 2);
+		let slow = walk(30, 2);
+
+		assert_eq!(fast.step_counter, 240);
+		assert_eq!(slow.step_counter, 240);
+		assert!((fast.player.position - slow.player.position).magnitude() < 1e-3);
+		assert!(fast.pose().chain_angles == slow.pose().chain_angles);
+	}
+
+	#[test]
+	fn scene_queries_find_what_moves() {
+		let frame = PhysicsFrame::frame_zero(16.0 / 9.0, 3);
+		let physics = PhysicsContext::empty();
+
+		let player = Aabb { min: frame.player.position, max: frame.player.position };
+		assert!(frame.overlapping(&physics, &player) == vec![ObjectId::Player]);
+
+		// straight down through the stack of boxes
+		//
+		let above = frame.bodies[0].position + Vector3::new(0.0, 20.0, 0.0);
+		let hits: Vec<ObjectId> = frame.raycast_bounds(&physics, above, Vector3::new(0.0, -1.0, 0.0), 30.0).into_iter().map(|hit| hit.0).collect();
+		assert!(hits == vec![ObjectId::Body(2), ObjectId::Body(1), ObjectId::Body(0)]);
+	}
+
+	#[test]
+	fn rays_hit_the_top_of_the_stack_and_picks_retarget_a_chain() {
+		let frame = PhysicsFrame::frame_zero(16.0 / 9.0, 3);
+		let physics = PhysicsContext::empty();
+
+		let above = frame.bodies[2].position + Vector3::new(0.0, 20.0, 0.0);
+		let hit = frame.raycast(&physics, above, Vector3::new(0.0, -2.0, 0.0), 30.0, |_| true).unwrap();
+		assert_eq!(hit.object, ObjectId::Body(2));
+		assert!((hit.normal - Vector3::new(0.0, 1.0, 0.0)).magnitude() < 1e-4);
+		assert!((hit.point.y - (frame.bodies[2].position.y + 1.0)).abs() < 1e-3);
+		assert!((hit.distance - (above.y - hit.point.y)).abs() < 1e-3);
+
+		assert!(frame.raycast(&physics, above, Vector3::new(0.0, -1.0, 0.0), 30.0, |object| object != ObjectId::Body(2)).unwrap().object == ObjectId::Body(1));
+		assert!(frame.raycast(&physics, above, Vector3::new(0.0, 1.0, 0.0), 30.0, |_| true).is_none());
+
+		// picking the stack sends the chain nearest it reaching
+		//
+		let mut picked = frame.clone();
+		picked.pick(&physics, above, Vector3::new(0.0, -1.0, 0.0));
+		let changed: Vec<usize> = (0..frame.ik_chains.len()).filter(|&n| picked.ik_chains[n].state != frame.ik_chains[n].state).collect();
+		assert_eq!(changed.len(), 1);
+		match picked.ik_chains[changed[0]].state {
+			State::Seeking { .. } => (),
+			_                     => panic!("chain {} isn't reaching for the pick", changed[0]),
+		}
+	}
+
+	#[test]
+	fn uneven_input_draws_between_the_last_two_steps() {
+		let physics = PhysicsContext::empty();
+		let mut frame = Arc::new(PhysicsFrame::frame_zero(16.0 / 9.0, 3));
+
+		// input frames that don't line up with steps leave time over in the accumulator
+		//
+		for n in 0..100 {
+			let elapsed = if n % 2 == 0 { 1.0 / 90.0 } else { 1.0 / 200.0 };
+			let input = InputFrame { elapsed: elapsed, movement_delta: Vector2::new(1.0, 0.5), .. InputFrame::frame_zero() };
+			frame = Arc::new(PhysicsFrame::new(&physics, frame, Arc::new(input)));
+		}
+		let previous = frame.previous.as_ref().unwrap().player_position;
+		let latest = frame.player.position;
+		assert!((latest - previous).magnitude() > 1e-3);
+
+		let alpha = frame.alpha(0.0);
+		assert!(alpha > 0.0 && alpha < 1.0, "alpha {}", alpha);
+
+		// and the blend moves on towards the latest step as time passes before the next frame
+		//
+		for &since in &[0.0, frame.dt * 0.1, frame.dt * 0.5, frame.dt] {
+			let alpha = frame.alpha(since);
+			let blended = frame.interpolated(alpha).player.position;
+
+			assert!((blended - (previous + (latest - previous) * alpha)).magnitude() < 1e-4);
+			assert!((blended - previous).magnitude() <= (latest - previous).magnitude() + 1e-4);
+			assert!((blended - latest).magnitude() <= (latest - previous).magnitude() + 1e-4);
+		}
+		assert_eq!(frame.alpha(frame.dt), 1.0);
+	}
+
+	#[test]
+	fn interpolation_blends_the_last_two_steps() {
+		let mut frame = walk(120, 1);
+		let previous = frame.player.position;
+		frame.previous = Some(Pose { player_position: previous + Vector3::new(-2.0, 0.0, 0.0), .. frame.pose() });
+		frame.accumulator = frame.dt * 0.25;
+
+		let blended = frame.interpolated(frame.alpha(0.0)).player.position;
+		assert!((blended - (previous + Vector3::new(-1.5, 0.0, 0.0))).magnitude() < 1e-4);
+	}
 }
@@ -0,0 +1,128 @@
+use cgmath::{Point3, Vector3};
+
+use physics::bvh::{Bvh};
+use physics::capsule::{Capsule};
+use physics::integration::{GRAVITY, approach, integrate};
+
+
+// position is the bottom of this
+//
+pub const SHAPE: Capsule = Capsule { radius: 1.0, height: 4.0 };
+
+pub const SPAWN: Point3<f32> = Point3 { x: 0.0, y: 1.0, z: 0.0 };
+
+// units per second with a key held or a stick pushed all the way
+//
+pub const SPEED: f32 = 12.0;
+
+const JUMP_SPEED:          f32 = 12.0;   // about 2.4 units high at this gravity
+const GROUND_ACCELERATION: f32 = 100.0;
+const AIR_ACCELERATION:    f32 = 25.0;   // a little steering mid jump
+const GROUND_REACH:        f32 = 0.05;   // ground closer than this holds the player up
+const STEP_DOWN:           f32 = 0.5;    // walking down slopes and steps keeps to the ground within this
+const KILL_HEIGHT:         f32 = -100.0; // fallen out of the level, start over
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Player {
+	pub position: Point3<f32>,
+	pub velocity: Vector3<f32>,
+	// on walkable ground, the only place jumping works
+	pub grounded: bool,
+}
+
+impl Player {
+	pub fn new(position: Point3<f32>) -> Player {
+		Player {
+			position: position,
+			velocity: Vector3::new(0.0, 0.0, 0.0),
+			grounded: false,
+		}
+	}
+
+	// one fixed step, `wish_velocity` is where the controls point, flat and up to SPEED long
+	//
+	pub fn step(&self, level: &Bvh, wish_velocity: Vector3<f32>, jump: bool, dt: f32) -> Player {
+		let jump = jump && self.grounded;
+		let standing = self.grounded && !jump;
+
+		let (acceleration, vertical) = match (standing, jump) {
+			(true, _)      => (GROUND_ACCELERATION, 0.0),
+			(false, true)  => (GROUND_ACCELERATION, JUMP_SPEED),
+			(false, false) => (AIR_ACCELERATION,    self.velocity.y),
+		};
+		let horizontal = approach(Vector3 { y: 0.0, .. self.velocity }, wish_velocity, acceleration * dt);
+		let gravity = if standing { 0.0 } else { -GRAVITY };
+
+		let (target, _) = integrate(self.position, Vector3 { y: vertical, .. horizontal }, Vector3::new(0.0, gravity, 0.0), dt);
+
+		let mut position = SHAPE.slide(level, self.position, target - self.position);
+		if standing && SHAPE.ground(level, position, STEP_DOWN).is_some() {
+			position = SHAPE.slide(level, position, Vector3::new(0.0, -STEP_DOWN, 0.0));
+		}
+		if position.y < KILL_HEIGHT { return Player::new(SPAWN) }
+
+		// whatever the level took out of the motion doesn't carry over into the next step
+		//
+		let velocity = (position - self.position) / dt;
+		let rising = !standing && velocity.y > 0.0;
+
+		Player {
+			position: position,
+			velocity: velocity,
+			grounded: !rising && SHAPE.ground(level, position, GROUND_REACH).is_some(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use cgmath::{Point3, Vector3};
+
+	use physics::bvh::{Bvh};
+	use physics::bvh::tests::{room};
+	use super::{Player, SPAWN, SPEED};
+
+
+	const DT: f32 = 1.0 / 120.0;
+
+	fn run(level: &Bvh, player: Player, wish_velocity: Vector3<f32>, steps: usize) -> Player {
+		(0..steps).fold(player, |player, _| player.step(level, wish_velocity, false, DT))
+	}
+
+	#[test]
+	fn falls_lands_and_jumps() {
+		let level = room();
+		let still = Vector3::new(0.0, 0.0, 0.0);
+
+		let landed = run(&level, Player::new(SPAWN), still, 120);
+		assert!(landed.grounded);
+		assert!(landed.position.y >= 0.0 && landed.position.y < 0.05, "landed at {:?}", landed.position);
+
+		let mut player = landed.step(&level, still, true, DT);
+		assert!(!player.grounded);
+
+		let mut highest = player.position.y;
+		for _ in 0..120 {
+			player = player.step(&level, still, false, DT);
+			highest = highest.max(player.position.y);
+		}
+		assert!(highest > 2.0 && highest < 3.0, "peaked at {}", highest);
+		assert!(player.grounded);
+
+		// jumping in the air does nothing
+		//
+		let falling = Player::new(Point3::new(0.0, 10.0, 0.0));
+		assert!(falling.step(&level, still, true, DT).velocity.y < 0.0);
+	}
+
+	#[test]
+	fn walls_stop_walking() {
+		let level = room();
+		let landed = run(&level, Player::new(SPAWN), Vector3::new(0.0, 0.0, 0.0), 60);
+
+		let pushed = run(&level, landed, Vector3::new(SPEED, 0.0, 0.0), 240);
+		assert!(pushed.position.x < 4.0 && pushed.position.x > 3.9, "stopped at {:?}", pushed.position);
+		assert!(pushed.velocity.x.abs() < 1e-3);
+		assert!(pushed.grounded);
+	}
+}
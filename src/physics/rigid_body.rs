@@ -0,0 +1,103 @@
+use cgmath::{InnerSpace, Matrix, Matrix3, One, Point3, Quaternion, SquareMatrix, Vector3};
+
+use physics::aabb::{Aabb};
+use physics::integration::{GRAVITY, integrate};
+use physics::shape::{Shape};
+
+
+// per second, a little drag so nothing spins or rolls forever
+//
+const LINEAR_DAMPING:  f32 = 0.05;
+const ANGULAR_DAMPING: f32 = 0.2;
+
+#[derive(Clone, Debug)]
+pub struct RigidBody {
+	pub shape:            Shape,
+	pub position:         Point3<f32>, // the centre of mass
+	pub orientation:      Quaternion<f32>,
+	pub velocity:         Vector3<f32>,
+	pub angular_velocity: Vector3<f32>, // radians per second about each world axis
+
+	// 0 for bodies nothing can move
+	pub inverse_mass:     f32,
+	// the diagonal of the inverse inertia tensor in body space
+	pub inverse_inertia:  Vector3<f32>,
+
+	// 0 stops dead, 1 bounces back as fast as it came in
+	pub restitution:      f32,
+	pub friction:         f32,
+}
+
+impl RigidBody {
+	// a mass of 0 makes it immovable
+	//
+	pub fn new(shape: Shape, position: Point3<f32>, mass: f32) -> RigidBody {
+		let (inverse_mass, inverse_inertia) = if mass > 0.0 {
+			(1.0 / mass, shape.inverse_inertia(mass))
+		} else {
+			(0.0, Vector3::new(0.0, 0.0, 0.0))
+		};
+
+		RigidBody {
+			shape:            shape,
+			position:         position,
+			orientation:      Quaternion::one(),
+			velocity:         Vector3::new(0.0, 0.0, 0.0),
+			angular_velocity: Vector3::new(0.0, 0.0, 0.0),
+			inverse_mass:     inverse_mass,
+			inverse_inertia:  inverse_inertia,
+			restitution:      0.3,
+			friction:         0.6,
+		}
+	}
+
+	pub fn bounds(&self) -> Aabb {
+		self.shape.bounds(self.position, self.orientation)
+	}
+
+	pub fn inverse_inertia_world(&self) -> Matrix3<f32> {
+		let rotation = Matrix3::from(self.orientation);
+
+		rotation * Matrix3::from_diagonal(self.inverse_inertia) * rotation.transpose()
+	}
+
+	// the velocity of the point `offset` from the centre of mass
+	//
+	pub fn velocity_at(&self, offset: Vector3<f32>) -> Vector3<f32> {
+		self.velocity + self.angular_velocity.cross(offset)
+	}
+
+	pub fn apply_impulse(&mut self, impulse: Vector3<f32>, offset: Vector3<f32>) {
+		self.velocity = self.velocity + impulse * self.inverse_mass;
+		self.angular_velocity = self.angular_velocity + self.inverse_inertia_world() * offset.cross(impulse);
+	}
+
+	// gravity into the velocity, before contacts get to cancel it out
+	//
+	pub fn accelerate(&self, dt: f32) -> RigidBody {
+		if self.inverse_mass == 0.0 { return self.clone() }
+
+		RigidBody { velocity: self.velocity + Vector3::new(0.0, -GRAVITY, 0.0) * dt, .. self.clone() }
+	}
+
+	// moves along the (already solved) velocities
+	//
+	pub fn integrate(&self, dt: f32) -> RigidBody {
+		if self.inverse_mass == 0.0 { return self.clone() }
+
+		let (position, velocity) = integrate(self.position, self.velocity, Vector3::new(0.0, 0.0, 0.0), dt);
+
+		// dq/dt = w q / 2, with w as a pure quaternion
+		//
+		let spin = Quaternion::from_sv(0.0, self.angular_velocity) * self.orientation * (0.5 * dt);
+		let orientation = (self.orientation + spin).normalize();
+
+		RigidBody {
+			position:         position,
+			orientation:      orientation,
+			velocity:         velocity * (1.0 / (1.0 + LINEAR_DAMPING * dt)),
+			angular_velocity: self.angular_velocity * (1.0 / (1.0 + ANGULAR_DAMPING * dt)),
+			.. self.clone()
+		}
+	}
+}
@@ -0,0 +1,347 @@
+use std::f32::{INFINITY};
+use std::f32::consts::{PI};
+use std::sync::{Arc};
+
+use cgmath::{InnerSpace, Point3, Quaternion, Vector3};
+use glium::backend::{Facade};
+use glium::{IndexBuffer, VertexBuffer};
+use glium::index::{PrimitiveType};
+
+use model::{Model};
+use physics::aabb::{Aabb};
+use render::vertices::{ForwardVertex};
+
+
+// what a rigid body is made of, in body space with its centre of mass at the origin
+//
+#[derive(Clone, Debug)]
+pub enum Shape {
+	Sphere(f32),
+	// boxes are hulls of their eight corners
+	Hull(Arc<Hull>),
+}
+
+// a convex polyhedron, kept as corners and outward face planes
+//
+#[derive(Debug)]
+pub struct Hull {
+	pub vertices: Vec<Vector3<f32>>,
+	pub faces:    Vec<Face>,
+}
+
+#[derive(Debug)]
+pub struct Face {
+	pub normal:   Vector3<f32>,
+	// normal.dot(x) == offset for x on the face
+	pub offset:   f32,
+	// indices into the hull's vertices, counter-clockwise seen from outside
+	pub vertices: Vec<usize>,
+}
+
+impl Shape {
+	pub fn sphere(radius: f32) -> Shape {
+		Shape::Sphere(radius)
+	}
+
+	pub fn cuboid(half_extents: Vector3<f32>) -> Shape {
+		let h = half_extents;
+		let corners: Vec<_> = (0..8).map(|n| Vector3::new(
+			if n & 1 == 0 { -h.x } else { h.x },
+			if n & 2 == 0 { -h.y } else { h.y },
+			if n & 4 == 0 { -h.z } else { h.z }
+		)).collect();
+
+		Shape::Hull(Arc::new(Hull::new(&corners)))
+	}
+
+	pub fn hull(points: &[Vector3<f32>]) -> Shape {
+		Shape::Hull(Arc::new(Hull::new(points)))
+	}
+
+	// the diagonal of the inverse inertia tensor in body space, hulls are treated as their
+	// bounding box which is exact for boxes and close enough for anything roughly box shaped
+	//
+	pub fn inverse_inertia(&self, mass: f32) -> Vector3<f32> {
+		let inertia = match *self {
+			Shape::Sphere(radius) => {
+				let moment = 0.4 * mass * radius * radius;
+				Vector3::new(moment, moment, moment)
+			},
+			Shape::Hull(ref hull) => {
+				let points: Vec<_> = hull.vertices.iter().map(|&v| Point3::new(v.x, v.y, v.z)).collect();
+				let bounds = Aabb::from_points(&points);
+				let size = bounds.max - bounds.min;
+				let (x2, y2, z2) = (size.x * size.x, size.y * size.y, size.z * size.z);
+
+				Vector3::new(y2 + z2, x2 + z2, x2 + y2) * (mass / 12.0)
+			},
+		};
+		Vector3::new(1.0 / inertia.x, 1.0 / inertia.y, 1.0 / inertia.z)
+	}
+
+	pub fn bounds(&self, position: Point3<f32>, orientation: Quaternion<f32>) -> Aabb {
+		match *self {
+			Shape::Sphere(radius) => Aabb { min: position, max: position }.expand(radius),
+			Shape::Hull(ref hull) => {
+				let corners: Vec<_> = hull.vertices.iter().map(|&v| position + orientation * v).collect();
+				Aabb::from_points(&corners)
+			},
+		}
+	}
+
+	// flat shaded hulls and a smooth sphere, in body space
+	//
+	pub fn mesh(&self) -> (Vec<ForwardVertex>, Vec<u32>) {
+		let vertex = |position: Vector3<f32>, normal: Vector3<f32>| ForwardVertex {
+			position: [position.x, position.y, position.z],
+			normal:   [normal.x, normal.y, normal.z],
+		};
+		let mut vertices = Vec::new();
+		let mut indices = Vec::new();
+
+		match *self {
+			Shape::Sphere(radius) => {
+				const RINGS:    usize = 12;
+				const SEGMENTS: usize = 24;
+
+				for ring in 0..RINGS + 1 {
+					let polar = PI * (ring as f32) / (RINGS as f32);
+					for segment in 0..SEGMENTS + 1 {
+						let azimuth = 2.0 * PI * (segment as f32) / (SEGMENTS as f32);
+						let normal = Vector3::new(polar.sin() * azimuth.cos(), polar.cos(), polar.sin() * azimuth.sin());
+
+						vertices.push(vertex(normal * radius, normal));
+					}
+				}
+				for ring in 0..RINGS {
+					for segment in 0..SEGMENTS {
+						let top    = (ring * (SEGMENTS + 1) + segment) as u32;
+						let bottom = top + (SEGMENTS + 1) as u32;
+
+						indices.extend_from_slice(&[top, bottom + 1, bottom, top, top + 1, bottom + 1]);
+					}
+				}
+			},
+			Shape::Hull(ref hull) => {
+				for face in &hull.faces {
+					let base = vertices.len() as u32;
+					for &index in &face.vertices {
+						vertices.push(vertex(hull.vertices[index], face.normal));
+					}
+					for n in 1..face.vertices.len() as u32 - 1 {
+						indices.extend_from_slice(&[base, base + n, base + n + 1]);
+					}
+				}
+			},
+		}
+		(vertices, indices)
+	}
+
+	// where a ray with a normalized `direction` first hits the outside, with the normal there,
+	// rays starting inside don't hit
+	//
+	pub fn ray_hit(&self, position: Point3<f32>, orientation: Quaternion<f32>, origin: Point3<f32>, direction: Vector3<f32>) -> Option<(f32, Vector3<f32>)> {
+		match *self {
+			Shape::Sphere(radius) => sphere_ray_hit(position, radius, origin, direction),
+			Shape::Hull(ref hull) => {
+				let to_body = orientation.conjugate();
+
+				hull.ray_hit(to_body * (origin - position), to_body * direction).map(|(distance, normal)| (distance, orientation * normal))
+			},
+		}
+	}
+
+	pub fn model<F: Facade>(&self, facade: &F) -> Model {
+		let (vertices, indices) = self.mesh();
+
+		Model {
+			vertex_buffer: VertexBuffer::new(facade, &vertices).unwrap(),
+			index_buffer:  IndexBuffer ::new(facade, PrimitiveType::TrianglesList, &indices).unwrap(),
+		}
+	}
+}
+
+impl Hull {
+	// every plane through three of the points with all the others behind it is a face,
+	// slow but hulls are small and only built once
+	//
+	pub fn new(points: &[Vector3<f32>]) -> Hull {
+		const EPSILON: f32 = 1e-4;
+
+		let mut unique: Vec<Vector3<f32>> = Vec::new();
+		for &point in points {
+			if !unique.iter().any(|&other| (other - point).magnitude2() < EPSILON * EPSILON) { unique.push(point) }
+		}
+		let points = &unique[..];
+
+		let mut faces: Vec<Face> = Vec::new();
+		for i in 0..points.len() {
+			for j in i + 1..points.len() {
+				for k in j + 1..points.len() {
+					let normal = (points[j] - points[i]).cross(points[k] - points[i]);
+					if normal.magnitude2() < EPSILON * EPSILON { continue }
+
+					let normal = normal.normalize();
+					let offset = normal.dot(points[i]);
+					let above = points.iter().any(|&p| normal.dot(p) - offset >  EPSILON);
+					let below = points.iter().any(|&p| normal.dot(p) - offset < -EPSILON);
+
+					let (normal, offset) = match (above, below) {
+						(true, true)  => continue,
+						(true, false) => (-normal, -offset),
+						(false, _)    => (normal, offset),
+					};
+					if faces.iter().any(|face| (face.normal - normal).magnitude2() < EPSILON) { continue }
+
+					let on_face = (0..points.len()).filter(|&n| (normal.dot(points[n]) - offset).abs() <= EPSILON).collect();
+					faces.push(Face { normal: normal, offset: offset, vertices: on_face });
+				}
+			}
+		}
+
+		// only corners are kept, points inside the hull or in the middle of a face or edge go
+		//
+		let mut vertices = Vec::new();
+		let mut remap = vec![None; points.len()];
+		for face in &mut faces {
+			let centre = face.vertices.iter().fold(Vector3::new(0.0, 0.0, 0.0), |sum, &n| sum + points[n]) / (face.vertices.len() as f32);
+			let u = (points[face.vertices[0]] - centre).normalize();
+			let w = face.normal.cross(u);
+			let angle = |n: usize| {
+				let offset = points[n] - centre;
+				offset.dot(w).atan2(offset.dot(u))
+			};
+			face.vertices.sort_by(|&a, &b| angle(a).partial_cmp(&angle(b)).unwrap());
+
+			let corners: Vec<usize> = (0..face.vertices.len()).filter(|&n| {
+				let previous = points[face.vertices[(n + face.vertices.len() - 1) % face.vertices.len()]];
+				let next = points[face.vertices[(n + 1) % face.vertices.len()]];
+				let point = points[face.vertices[n]];
+				(point - previous).cross(next - point).magnitude2() > EPSILON * EPSILON
+			}).map(|n| face.vertices[n]).collect();
+
+			face.vertices = corners.into_iter().map(|n| {
+				if remap[n].is_none() {
+					vertices.push(points[n]);
+					remap[n] = Some(vertices.len() - 1);
+				}
+				remap[n].unwrap()
+			}).collect();
+		}
+
+		Hull {
+			vertices: vertices,
+			faces:    faces,
+		}
+	}
+
+	// the ray is clipped by every face plane in turn, what's left starts where it enters
+	//
+	fn ray_hit(&self, origin: Vector3<f32>, direction: Vector3<f32>) -> Option<(f32, Vector3<f32>)> {
+		let mut near: f32 = 0.0;
+		let mut far = INFINITY;
+		let mut normal = None;
+
+		for face in &self.faces {
+			let outside = face.normal.dot(origin) - face.offset;
+			let approach = face.normal.dot(direction);
+
+			if approach == 0.0 {
+				if outside > 0.0 { return None }
+				continue;
+			}
+			let distance = -outside / approach;
+			if approach < 0.0 {
+				if distance > near { near = distance; normal = Some(face.normal) }
+			} else {
+				far = far.min(distance);
+			}
+			if near > far { return None }
+		}
+		normal.map(|normal| (near, normal))
+	}
+
+	// how far `point` (in body space) is outside the hull and through which face, negative inside
+	//
+	pub fn separation(&self, point: Vector3<f32>) -> (f32, &Face) {
+		let mut best = (self.faces[0].normal.dot(point) - self.faces[0].offset, &self.faces[0]);
+		for face in &self.faces[1..] {
+			let distance = face.normal.dot(point) - face.offset;
+			if distance > best.0 { best = (distance, face) }
+		}
+		best
+	}
+}
+
+// where a ray with a normalized `direction` first hits the outside of a sphere
+//
+pub fn sphere_ray_hit(centre: Point3<f32>, radius: f32, origin: Point3<f32>, direction: Vector3<f32>) -> Option<(f32, Vector3<f32>)> {
+	let offset = origin - centre;
+	let b = offset.dot(direction);
+	let c = offset.magnitude2() - radius * radius;
+	if c <= 0.0 || b > 0.0 { return None } // inside, or outside and heading away
+
+	let discriminant = b * b - c;
+	if discriminant < 0.0 { return None }
+
+	let distance = -b - discriminant.sqrt();
+	Some((distance, (offset + direction * distance) / radius))
+}
+
+#[cfg(test)]
+mod tests {
+	use cgmath::{InnerSpace, Vector3};
+
+	use super::{Shape};
+
+
+	// every triangle should face away from the centre, or it gets culled
+	//
+	fn faces_outwards(shape: &Shape) -> bool {
+		let (vertices, indices) = shape.mesh();
+		let position = |n: u32| {
+			let p = vertices[n as usize].position;
+			Vector3::new(p[0], p[1], p[2])
+		};
+
+		indices.chunks(3).all(|triangle| {
+			let (a, b, c) = (position(triangle[0]), position(triangle[1]), position(triangle[2]));
+			let area = (b - a).cross(c - a);
+
+			area.magnitude2() < 1e-10 || area.dot(a + b + c) > 0.0
+		})
+	}
+
+	#[test]
+	fn boxes_and_hulls_are_built_from_their_corners() {
+		let cuboid = Shape::cuboid(Vector3::new(1.0, 2.0, 3.0));
+		match cuboid {
+			Shape::Hull(ref hull) => {
+				assert_eq!(hull.vertices.len(), 8);
+				assert_eq!(hull.faces.len(), 6);
+				assert!(hull.faces.iter().all(|face| face.vertices.len() == 4));
+			},
+			_ => panic!("a box should be a hull"),
+		}
+
+		// the middle, and the middle of an edge, aren't corners
+		//
+		let points = [
+			Vector3::new(0.0, -0.5, 0.0), Vector3::new(0.0, -1.0, -1.0),
+			Vector3::new(-1.0, -1.0, -1.0), Vector3::new(1.0, -1.0, -1.0), Vector3::new(0.0, -1.0, 1.0), Vector3::new(0.0, 1.0, 0.0),
+		];
+		match Shape::hull(&points) {
+			Shape::Hull(ref hull) => {
+				assert_eq!(hull.vertices.len(), 4);
+				assert_eq!(hull.faces.len(), 4);
+				assert!(hull.separation(Vector3::new(0.0, -0.5, 0.0)).0 < 0.0);
+				assert!(hull.separation(Vector3::new(0.0, 2.0, 0.0)).0 > 0.0);
+			},
+			_ => panic!("should be a hull"),
+		}
+
+		assert!(faces_outwards(&cuboid));
+		assert!(faces_outwards(&Shape::hull(&points)));
+		assert!(faces_outwards(&Shape::sphere(2.0)));
+	}
+}
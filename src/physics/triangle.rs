@@ -0,0 +1,152 @@
+use cgmath::{InnerSpace, Point3, Vector3};
+
+use physics::aabb::{Aabb};
+
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Triangle {
+	pub a: Point3<f32>,
+	pub b: Point3<f32>,
+	pub c: Point3<f32>,
+}
+
+impl Triangle {
+	pub fn new(a: Point3<f32>, b: Point3<f32>, c: Point3<f32>) -> Triangle {
+		Triangle { a: a, b: b, c: c }
+	}
+
+	// not normalized, zero for degenerate triangles
+	//
+	pub fn cross(&self) -> Vector3<f32> {
+		(self.b - self.a).cross(self.c - self.a)
+	}
+
+	pub fn bounds(&self) -> Aabb {
+		Aabb::from_points(&[self.a, self.b, self.c])
+	}
+
+	pub fn centroid(&self) -> Point3<f32> {
+		self.a + ((self.b - self.a) + (self.c - self.a)) * (1.0 / 3.0)
+	}
+
+	// how far along `direction` the ray from `origin` hits either side, Möller–Trumbore
+	//
+	pub fn ray_hit(&self, origin: Point3<f32>, direction: Vector3<f32>) -> Option<f32> {
+		let (ab, ac) = (self.b - self.a, self.c - self.a);
+		let p = direction.cross(ac);
+		let determinant = ab.dot(p);
+		if determinant.abs() < 1e-12 { return None } // parallel
+
+		let to_origin = origin - self.a;
+		let u = to_origin.dot(p) / determinant;
+		if u < 0.0 || u > 1.0 { return None }
+
+		let q = to_origin.cross(ab);
+		let v = direction.dot(q) / determinant;
+		if v < 0.0 || u + v > 1.0 { return None }
+
+		let distance = ac.dot(q) / determinant;
+		if distance >= 0.0 { Some(distance) } else { None }
+	}
+
+	// Ericson, Real-Time Collision Detection 5.1.5, walks the voronoi regions of the vertices and edges
+	//
+	pub fn closest_point(&self, p: Point3<f32>) -> Point3<f32> {
+		let (a, b, c) = (self.a, self.b, self.c);
+		let ab = b - a;
+		let ac = c - a;
+
+		let ap = p - a;
+		let d1 = ab.dot(ap);
+		let d2 = ac.dot(ap);
+		if d1 <= 0.0 && d2 <= 0.0 { return a }
+
+		let bp = p - b;
+		let d3 = ab.dot(bp);
+		let d4 = ac.dot(bp);
+		if d3 >= 0.0 && d4 <= d3 { return b }
+
+		let vc = d1 * d4 - d3 * d2;
+		if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 { return a + ab * (d1 / (d1 - d3)) }
+
+		let cp = p - c;
+		let d5 = ab.dot(cp);
+		let d6 = ac.dot(cp);
+		if d6 >= 0.0 && d5 <= d6 { return c }
+
+		let vb = d5 * d2 - d1 * d6;
+		if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 { return a + ac * (d2 / (d2 - d6)) }
+
+		let va = d3 * d6 - d5 * d4;
+		if va <= 0.0 && d4 - d3 >= 0.0 && d5 - d6 >= 0.0 {
+			return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+		}
+
+		let denominator = 1.0 / (va + vb + vc);
+		a + ab * (vb * denominator) + ac * (vc * denominator)
+	}
+
+	// the closest points between the segment p q and the triangle, (on the segment, on the triangle)
+	//
+	pub fn closest_to_segment(&self, p: Point3<f32>, q: Point3<f32>) -> (Point3<f32>, Point3<f32>) {
+		// a segment through the face touches it, otherwise the closest points involve
+		// an end of the segment or an edge of the triangle
+		//
+		let normal = self.cross();
+		let p_side = (p - self.a).dot(normal);
+		let q_side = (q - self.a).dot(normal);
+		if p_side * q_side <= 0.0 && p_side != q_side {
+			let crossing = p + (q - p) * (p_side / (p_side - q_side));
+			if (self.closest_point(crossing) - crossing).magnitude2() < 1e-10 { return (crossing, crossing) }
+		}
+
+		let candidates = [
+			(p, self.closest_point(p)),
+			(q, self.closest_point(q)),
+			closest_between_segments(p, q, self.a, self.b),
+			closest_between_segments(p, q, self.b, self.c),
+			closest_between_segments(p, q, self.c, self.a),
+		];
+		let mut closest = candidates[0];
+		for &candidate in &candidates[1..] {
+			if (candidate.0 - candidate.1).magnitude2() < (closest.0 - closest.1).magnitude2() { closest = candidate }
+		}
+		closest
+	}
+}
+
+// Ericson 5.1.9, the closest points on p1 q1 and p2 q2
+//
+fn closest_between_segments(p1: Point3<f32>, q1: Point3<f32>, p2: Point3<f32>, q2: Point3<f32>) -> (Point3<f32>, Point3<f32>) {
+	const EPSILON: f32 = 1e-8;
+
+	let clamp = |x: f32| x.max(0.0).min(1.0);
+
+	let d1 = q1 - p1;
+	let d2 = q2 - p2;
+	let r = p1 - p2;
+	let a = d1.dot(d1);
+	let e = d2.dot(d2);
+	let f = d2.dot(r);
+
+	let (s, t) = if a <= EPSILON && e <= EPSILON {
+		(0.0, 0.0)
+	} else if a <= EPSILON {
+		(0.0, clamp(f / e))
+	} else {
+		let c = d1.dot(r);
+		if e <= EPSILON {
+			(clamp(-c / a), 0.0)
+		} else {
+			let b = d1.dot(d2);
+			let denominator = a * e - b * b;
+			let s = if denominator != 0.0 { clamp((b * f - c * e) / denominator) } else { 0.0 };
+			let t = (b * s + f) / e;
+
+			if t < 0.0      { (clamp(-c / a), 0.0) }
+			else if t > 1.0 { (clamp((b - c) / a), 1.0) }
+			else            { (s, t) }
+		}
+	};
+	(p1 + d1 * s, p2 + d2 * t)
+}
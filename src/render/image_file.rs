@@ -0,0 +1,240 @@
+use std::cmp;
+use std::fs::{File};
+use std::io;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path};
+
+
+// an 8 bit RGBA image with the top row first, as image files expect
+// (GL hands rows back bottom first, see from_gl_rows)
+//
+pub struct Image {
+	pub width:  u32,
+	pub height: u32,
+	pub pixels: Vec<(u8, u8, u8, u8)>,
+}
+
+impl Image {
+	pub fn from_gl_rows(rows: Vec<Vec<(u8, u8, u8, u8)>>) -> Image {
+		let height = rows.len() as u32;
+		let width = rows.first().map_or(0, |row| row.len() as u32);
+
+		Image {
+			width:  width,
+			height: height,
+			pixels: rows.into_iter().rev().flat_map(|row| row.into_iter()).collect(),
+		}
+	}
+
+	// picks the format from the extension, .png or .ppm
+	//
+	pub fn write(&self, path: &str) -> io::Result<()> {
+		let mut writer = BufWriter::new(try!(File::create(path)));
+
+		match Path::new(path).extension().and_then(|extension| extension.to_str()) {
+			Some("png") => try!(self.write_png(&mut writer)),
+			Some("ppm") => try!(self.write_ppm(&mut writer)),
+			_ => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} should end in .png or .ppm", path))),
+		}
+		writer.flush()
+	}
+
+	// binary P6, alpha is dropped
+	//
+	pub fn write_ppm<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+		try!(write!(writer, "P6\n{} {}\n255\n", self.width, self.height));
+
+		let bytes: Vec<u8> = self.pixels.iter().flat_map(|&(r, g, b, _)| vec![r, g, b].into_iter()).collect();
+		writer.write_all(&bytes)
+	}
+
+	// RGBA with stored (uncompressed) deflate blocks, bigger than it needs to be but dependency free
+	//
+	pub fn write_png<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+		const SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+		const MAX_STORED_BLOCK: usize = 65535;
+
+		try!(writer.write_all(&SIGNATURE));
+
+		let mut header = Vec::new();
+		header.extend_from_slice(&be_u32(self.width));
+		header.extend_from_slice(&be_u32(self.height));
+		header.extend_from_slice(&[8, 6, 0, 0, 0]); // bit depth, RGBA, deflate, adaptive filtering, no interlace
+		try!(write_chunk(writer, b"IHDR", &header));
+
+		// every scanline starts with its filter type, 0 is none
+		//
+		let mut scanlines = Vec::with_capacity((self.height * (1 + 4 * self.width)) as usize);
+		for row in self.pixels.chunks(cmp::max(self.width, 1) as usize) {
+			scanlines.push(0);
+			for &(r, g, b, a) in row {
+				scanlines.extend_from_slice(&[r, g, b, a]);
+			}
+		}
+
+		let mut zlib = vec![0x78, 0x01];
+		let mut blocks = scanlines.chunks(MAX_STORED_BLOCK).peekable();
+		if blocks.peek().is_none() { zlib.extend_from_slice(&[1, 0, 0, 255, 255]) }
+		while let Some(block) = blocks.next() {
+			let length = block.len() as u16;
+			let last = if blocks.peek().is_none() { 1 } else { 0 };
+
+			zlib.push(last);
+			zlib.extend_from_slice(&[length as u8, (length >> 8) as u8, !length as u8, (!length >> 8) as u8]);
+			zlib.extend_from_slice(block);
+		}
+		zlib.extend_from_slice(&be_u32(adler32(&scanlines)));
+
+		try!(write_chunk(writer, b"IDAT", &zlib));
+		write_chunk(writer, b"IEND", &[])
+	}
+
+	// golden images are kept as .ppm so they can be read back without a png decoder
+	//
+	pub fn read_ppm(path: &str) -> io::Result<Image> {
+		let mut bytes = Vec::new();
+		try!(try!(File::open(path)).read_to_end(&mut bytes));
+
+		let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path, message));
+
+		// magic, width, height and max value, separated by whitespace and # comments,
+		// then a single whitespace byte before the pixels
+		//
+		let mut fields = Vec::new();
+		let mut position = 0;
+		while fields.len() < 4 {
+			while position < bytes.len() && (bytes[position] as char).is_whitespace() { position += 1 }
+			if position < bytes.len() && bytes[position] == b'#' {
+				while position < bytes.len() && bytes[position] != b'\n' { position += 1 }
+				continue;
+			}
+			let start = position;
+			while position < bytes.len() && !(bytes[position] as char).is_whitespace() { position += 1 }
+			if start == position { return Err(invalid("truncated header")) }
+
+			fields.push(String::from_utf8_lossy(&bytes[start..position]).into_owned());
+		}
+		position += 1;
+
+		if fields[0] != "P6" { return Err(invalid("not a binary (P6) ppm")) }
+		let (width, height) = match (fields[1].parse::<u32>(), fields[2].parse::<u32>()) {
+			(Ok(width), Ok(height)) => (width, height),
+			_ => return Err(invalid("bad dimensions")),
+		};
+		if fields[3] != "255" { return Err(invalid("only 8 bit ppms are supported")) }
+
+		let length = (width * height * 3) as usize;
+		if bytes.len() < position + length { return Err(invalid("truncated pixel data")) }
+
+		Ok(Image {
+			width:  width,
+			height: height,
+			pixels: bytes[position..position + length].chunks(3).map(|rgb| (rgb[0], rgb[1], rgb[2], 255)).collect(),
+		})
+	}
+
+	// how many pixels have a colour channel further than tolerance from other's, None if the sizes differ
+	// alpha is ignored since ppms don't keep it
+	//
+	pub fn mismatched_pixels(&self, other: &Image, tolerance: u8) -> Option<usize> {
+		if (self.width, self.height) != (other.width, other.height) { return None }
+
+		let differs = |a: u8, b: u8| (a as i16 - b as i16).abs() > tolerance as i16;
+
+		Some(self.pixels.iter().zip(other.pixels.iter()).filter(|&(&(r0, g0, b0, _), &(r1, g1, b1, _))| {
+			differs(r0, r1) || differs(g0, g1) || differs(b0, b1)
+		}).count())
+	}
+}
+
+fn write_chunk<W: Write>(writer: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+	let mut crc = Crc32::new();
+	crc.update(kind);
+	crc.update(data);
+
+	try!(writer.write_all(&be_u32(data.len() as u32)));
+	try!(writer.write_all(kind));
+	try!(writer.write_all(data));
+	writer.write_all(&be_u32(crc.finish()))
+}
+
+fn be_u32(value: u32) -> [u8; 4] {
+	[(value >> 24) as u8, (value >> 16) as u8, (value >> 8) as u8, value as u8]
+}
+
+fn adler32(data: &[u8]) -> u32 {
+	const MODULUS: u32 = 65521;
+
+	let (mut a, mut b) = (1, 0);
+	for &byte in data {
+		a = (a + byte as u32) % MODULUS;
+		b = (b + a) % MODULUS;
+	}
+	(b << 16) | a
+}
+
+struct Crc32 {
+	table: [u32; 256],
+	crc:   u32,
+}
+
+impl Crc32 {
+	fn new() -> Crc32 {
+		let mut table = [0; 256];
+		for n in 0..256 {
+			let mut c = n as u32;
+			for _ in 0..8 {
+				c = if c & 1 == 1 { 0xedb88320 ^ (c >> 1) } else { c >> 1 };
+			}
+			table[n] = c;
+		}
+		Crc32 { table: table, crc: 0xffffffff }
+	}
+
+	fn update(&mut self, data: &[u8]) {
+		for &byte in data {
+			self.crc = self.table[((self.crc ^ byte as u32) & 0xff) as usize] ^ (self.crc >> 8);
+		}
+	}
+
+	fn finish(&self) -> u32 {
+		self.crc ^ 0xffffffff
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::env;
+
+	use super::{Crc32, Image, adler32};
+
+
+	#[test]
+	fn checksums_match_known_values() {
+		let mut crc = Crc32::new();
+		crc.update(b"IEND");
+		assert_eq!(crc.finish(), 0xae426082);
+
+		assert_eq!(adler32(b"Wikipedia"), 0x11e60398);
+	}
+
+	#[test]
+	fn gl_rows_are_flipped_top_first() {
+		let image = Image::from_gl_rows(vec![vec![(0, 0, 0, 255), (1, 1, 1, 255)], vec![(2, 2, 2, 255), (3, 3, 3, 255)]]);
+
+		assert_eq!((image.width, image.height), (2, 2));
+		assert_eq!(image.pixels, vec![(2, 2, 2, 255), (3, 3, 3, 255), (0, 0, 0, 255), (1, 1, 1, 255)]);
+	}
+
+	#[test]
+	fn ppm_survives_a_round_trip() {
+		let image = Image { width: 3, height: 2, pixels: (0..6).map(|n| (n * 40, 255 - n, n, 255)).collect() };
+		let path = env::temp_dir().join("sg_image_file_round_trip.ppm");
+		let path = path.to_str().unwrap();
+
+		image.write(path).unwrap();
+		let read = Image::read_ppm(path).unwrap();
+
+		assert_eq!(read.mismatched_pixels(&image, 0), Some(0));
+	}
+}
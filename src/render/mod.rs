@@ -1,8 +1,10 @@
+pub use self::image_file::{Image};
 pub use self::render_context::{ModelId, RenderContext};
 pub use self::render_frame::{RenderFrame};
-pub use self::render_processor::{RenderProcessor};
+pub use self::render_processor::{RenderProcessor, window_builder};
 pub use self::render_token::{RenderToken};
 
+mod image_file;
 mod render_context;
 mod render_frame;
 pub mod render_processor;
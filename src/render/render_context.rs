@@ -8,8 +8,10 @@ use glium::backend::{Facade};
 use debug::{gnomon, indicator};
 use inverse_kinematics::{Chain};
 use model::{Model};
+use physics::{RigidBody};
 use unlit_model::{UnlitModel};
 use render::render_frame::{RenderFrame};
+use scene::{PLAYER_PATH, LEVEL_PATH, TREE_PATH};
 
 
 pub const DEPTH_DIMENSION: u32 = 2048;
@@ -20,15 +22,17 @@ pub enum ModelId {
 	Scene,
 	IKModel, // TODO: we are going to need more of these / a dynamic way to generate ids and load at a later time
 	Tree,
+	RigidBody(usize), // by index into PhysicsFrame::bodies
 
 	// DEBUG
 	Gnomon,
 	Indicator,
+	IndicatorIncomplete,
+	IndicatorFailed,
 }
 
 pub struct RenderContext {
 	pub q: Arc<MsQueue<RenderFrame>>, // TODO: make private and provide minimal decent api
-	window_size: (u32, u32), // TODO: maybe this should be a per RenderFrame parameter
 	pub models: HashMap<ModelId, Arc<Model>>,
 
 	// DEBUG
@@ -36,38 +40,35 @@ pub struct RenderContext {
 }
 
 impl RenderContext {
-	pub fn new<F: Facade>(facade: &F, q: Arc<MsQueue<RenderFrame>>, window_size: (u32, u32), ik_chains: &[Chain]) -> RenderContext {
-		let model_map = load_initial_models(facade, ik_chains);
+	pub fn new<F: Facade>(facade: &F, q: Arc<MsQueue<RenderFrame>>, ik_chains: &[Chain], bodies: &[RigidBody]) -> RenderContext {
+		let model_map = load_initial_models(facade, ik_chains, bodies);
 
 		// DEBUG
 		let mut unlit_models = HashMap::new();
 		unlit_models.insert(ModelId::Gnomon, Arc::new(gnomon::model(facade)));
-		unlit_models.insert(ModelId::Indicator, Arc::new(indicator::model(facade)));
+		unlit_models.insert(ModelId::Indicator,           Arc::new(indicator::model(facade, [0.2, 0.8, 0.2])));
+		unlit_models.insert(ModelId::IndicatorIncomplete, Arc::new(indicator::model(facade, [0.8, 0.8, 0.2])));
+		unlit_models.insert(ModelId::IndicatorFailed,     Arc::new(indicator::model(facade, [0.8, 0.2, 0.2])));
 
 		RenderContext {
 			q: q,
-			window_size: window_size,
 			models: model_map,
 
 			// DEBUG
 			unlit_models: unlit_models,
 		}
 	}
-
-	pub fn aspect_ratio(&self) -> f32 {
-		(self.window_size.0 as f32) / (self.window_size.1 as f32)
-	}
 }
 
 // TODO: don't pass in chains but make something like IntoModel
 //
-fn load_initial_models<F: Facade>(facade: &F, ik_chains: &[Chain]) -> HashMap<ModelId, Arc<Model>> {
+fn load_initial_models<F: Facade>(facade: &F, ik_chains: &[Chain], bodies: &[RigidBody]) -> HashMap<ModelId, Arc<Model>> {
 	let mut map = HashMap::new();
 
 	const MODEL_PATH_STRINGS: [(ModelId, &'static str); 3] = [
-		(ModelId::Player, "./data/player.obj"),
-		(ModelId::Scene,  "./data/level.obj"),
-		(ModelId::Tree,   "./data/tree.obj")
+		(ModelId::Player, PLAYER_PATH),
+		(ModelId::Scene,  LEVEL_PATH),
+		(ModelId::Tree,   TREE_PATH)
 	];
 	for &(model_id, path) in &MODEL_PATH_STRINGS {
 		let model = Arc::new(Model::new(facade, &Path::new(path)));
@@ -76,6 +77,9 @@ fn load_initial_models<F: Facade>(facade: &F, ik_chains: &[Chain]) -> HashMap<Mo
 	for chain in ik_chains {
 		map.insert(ModelId::IKModel, Arc::new(chain.model(facade)));
 	}
+	for (index, body) in bodies.iter().enumerate() {
+		map.insert(ModelId::RigidBody(index), Arc::new(body.shape.model(facade)));
+	}
 	map
 }
 
@@ -1,18 +1,16 @@
+use std::collections::{HashSet};
 use std::f32::{MAX, MIN};
-use std::f32::consts::{PI};
 use std::sync::{Arc};
 
 use cgmath;
-use cgmath::{Matrix, Matrix3, Matrix4, Vector3, Vector4, SquareMatrix, EuclideanSpace, InnerSpace, Rad};
-use rand::{SeedableRng, StdRng};
-use rand::distributions::{IndependentSample, Range};
+use cgmath::{Matrix, Matrix3, Matrix4, Vector3, Vector4, SquareMatrix, EuclideanSpace, InnerSpace};
 
-use context::{Context};
 use unlit_model::{UnlitModel};
-use inverse_kinematics::{State};
+use inverse_kinematics::{SolveStatus, State};
 use model::{Model};
-use physics::{PhysicsFrame};
-use render::render_context::{ModelId, DEPTH_DIMENSION};
+use physics::{Frustum, ObjectId, PhysicsContext, PhysicsFrame};
+use scene;
+use render::render_context::{ModelId, RenderContext, DEPTH_DIMENSION};
 use render::uniforms::{RenderUniforms, UnlitUniforms, ShadowUniforms};
 use render::uniform_wrappers::{UMatrix4, UVector3};
 use render::casts_shadow::{CastsShadow};
@@ -30,7 +28,11 @@ pub struct RenderFrame {
 }
 
 impl RenderFrame {
-	pub fn new(context: Arc<Context>, physics_frame: Arc<PhysicsFrame>) -> RenderFrame {
+	// `alpha` is how far between the physics frame's last two steps to draw, see PhysicsFrame::alpha
+	//
+	pub fn new(render: &RenderContext, physics: &PhysicsContext, physics_frame: Arc<PhysicsFrame>, alpha: f32) -> RenderFrame {
+		let physics_frame = physics_frame.interpolated(alpha);
+
 		let light_direction = physics_frame.light_direction;
 		let reverse_light_direction = light_direction * -1.0;
 
@@ -104,13 +106,18 @@ impl RenderFrame {
 		let projection = physics_frame.camera.projection.clone();
 		let view_projection = projection * view;
 
+		// only what the camera sees is drawn, and only what the light's view of the scene takes in casts shadows
+		//
+		let visible:   HashSet<ObjectId> = physics_frame.visible(physics, &Frustum::from_matrix(view_projection)).into_iter().collect();
+		let shadowing: HashSet<ObjectId> = physics_frame.visible(physics, &Frustum::from_matrix(shadow_view_projection)).into_iter().collect();
+
 		let scene_uniforms = RenderUniforms {
 			shadow:                UMatrix4(shadow_view_projection),
 			model:                 UMatrix4(Matrix4::identity()),
 			model_view_projection: UMatrix4(view_projection),
 		};
 
-		let translation = Matrix4::from_translation(physics_frame.player_position.to_vec());
+		let translation = Matrix4::from_translation(physics_frame.player.position.to_vec());
 
 		let up                  = Vector3::new(0f32, 1f32, 0f32);
 		let flat_view_direction = (Vector3 { y: 0f32, .. physics_frame.camera.view_direction() }).normalize();
@@ -133,41 +140,48 @@ impl RenderFrame {
 		};
 
 		let mut models = vec![
-			(context.render.models.get(&ModelId::Scene).unwrap().clone(), scene_uniforms.clone()),
-			(context.render.models.get(&ModelId::Player).unwrap().clone(), player_uniforms.clone()),
+			(render.models.get(&ModelId::Scene).unwrap().clone(), scene_uniforms.clone()),
+			(render.models.get(&ModelId::Player).unwrap().clone(), player_uniforms.clone()),
 		];
 		let mut shadow_casters = vec![
-			(	context.render.models.get(&ModelId::Scene).unwrap().clone() as Arc<CastsShadow>,
+			(	render.models.get(&ModelId::Scene).unwrap().clone() as Arc<CastsShadow>,
 				Box::new(scene_uniforms.clone()) as Box<ShadowUniforms>
 			),
-			(	context.render.models.get(&ModelId::Player).unwrap().clone() as Arc<CastsShadow>,
+			(	render.models.get(&ModelId::Player).unwrap().clone() as Arc<CastsShadow>,
 				Box::new(player_uniforms.clone()) as Box<ShadowUniforms>
 			)
 		];
 
-		{ // TODO: all this is constant
-			const D: f32 = 8f32;
-			const A: f32 = 40f32;
-
-			let seed: &[_] = &[2, 2, 2, 2];
-			let mut rng: StdRng = SeedableRng::from_seed(seed);
-			let range = Range::new(0f32, PI * 0.5);
-
-			let zs = [D * -3.0, D * 3.0, D * -2.0, D * 2.0, D, -D, 0.0, A, -A];
-
-			for &z in &zs {
-				let transform =
-					Matrix4::from_translation(Vector3::new(A, 0.0, z)) *
-					Matrix4::from_angle_y(Rad(range.ind_sample(&mut rng)));
+		for (index, transform) in scene::tree_transforms().into_iter().enumerate() {
+			let uniforms = RenderUniforms {
+				shadow:                UMatrix4(shadow_view_projection * transform),
+				model:                 UMatrix4(transform),
+				model_view_projection: UMatrix4(view_projection * transform),
+			};
+			if visible.contains(&ObjectId::Tree(index)) {
+				models.push((render.models.get(&ModelId::Tree).unwrap().clone(), uniforms.clone()));
+			}
+			if shadowing.contains(&ObjectId::Tree(index)) {
+				shadow_casters.push((
+					render.models.get(&ModelId::Tree).unwrap().clone() as Arc<CastsShadow>,
+					Box::new(uniforms.clone()) as Box<ShadowUniforms>
+				));
+			}
+		}
 
-				let uniforms = RenderUniforms {
-					shadow:                UMatrix4(shadow_view_projection * transform),
-					model:                 UMatrix4(transform),
-					model_view_projection: UMatrix4(view_projection * transform),
-				};
-				models.push((context.render.models.get(&ModelId::Tree).unwrap().clone(), uniforms.clone()));
+		for (index, body) in physics_frame.bodies.iter().enumerate() {
+			let transform = Matrix4::from_translation(body.position.to_vec()) * Matrix4::from(body.orientation);
+			let uniforms = RenderUniforms {
+				shadow:                UMatrix4(shadow_view_projection * transform),
+				model:                 UMatrix4(transform),
+				model_view_projection: UMatrix4(view_projection * transform),
+			};
+			if visible.contains(&ObjectId::Body(index)) {
+				models.push((render.models.get(&ModelId::RigidBody(index)).unwrap().clone(), uniforms.clone()));
+			}
+			if shadowing.contains(&ObjectId::Body(index)) {
 				shadow_casters.push((
-					context.render.models.get(&ModelId::Tree).unwrap().clone() as Arc<CastsShadow>,
+					render.models.get(&ModelId::RigidBody(index)).unwrap().clone() as Arc<CastsShadow>,
 					Box::new(uniforms.clone()) as Box<ShadowUniforms>
 				));
 			}
@@ -181,14 +195,15 @@ impl RenderFrame {
 			let player_uniforms = UnlitUniforms { model_view_projection: UMatrix4(svp),  shadow: None };
 
 			vec![
-				(context.render.unlit_models.get(&ModelId::Gnomon).unwrap().clone(), scene_uniforms),
-				(context.render.unlit_models.get(&ModelId::Gnomon).unwrap().clone(), player_uniforms),
+				(render.unlit_models.get(&ModelId::Gnomon).unwrap().clone(), scene_uniforms),
+				(render.unlit_models.get(&ModelId::Gnomon).unwrap().clone(), player_uniforms),
 			]
 		};
 
-		for chain in &physics_frame.ik_chains {
+		for (index, chain) in physics_frame.ik_chains.iter().enumerate() {
 			let transforms = chain.visible_joint_transforms();
 			let offset = Matrix4::from_translation(chain.position);
+			let (drawn, shadowed) = (visible.contains(&ObjectId::Chain(index)), shadowing.contains(&ObjectId::Chain(index)));
 
 			for joint in transforms {
 				let joint = offset * joint;
@@ -200,29 +215,46 @@ impl RenderFrame {
 					model:                 UMatrix4(joint),
 					model_view_projection: UMatrix4(mvp),
 				};
-				models.push((context.render.models.get(&ModelId::IKModel).unwrap().clone(), uniforms.clone()));
-				shadow_casters.push((
-					context.render.models.get(&ModelId::IKModel).unwrap().clone() as Arc<CastsShadow>,
-					Box::new(uniforms.clone()) as Box<ShadowUniforms>
-				));
+				if shadowed {
+					shadow_casters.push((
+						render.models.get(&ModelId::IKModel).unwrap().clone() as Arc<CastsShadow>,
+						Box::new(uniforms.clone()) as Box<ShadowUniforms>
+					));
+				}
+				if !drawn { continue }
+
+				models.push((render.models.get(&ModelId::IKModel).unwrap().clone(), uniforms.clone()));
 
 				let mvp = mvp * Matrix4::from_scale(2.0);
 
 				let unlit_uniforms = UnlitUniforms { model_view_projection: UMatrix4(mvp), shadow: Some(UMatrix4(shadow)) };
 
-				unlit_models.push((context.render.unlit_models.get(&ModelId::Gnomon).unwrap().clone(), unlit_uniforms));
+				unlit_models.push((render.unlit_models.get(&ModelId::Gnomon).unwrap().clone(), unlit_uniforms));
 			}
 
 			match chain.state {
-				State::Seeking { target, .. } | State::Waiting { target, .. } => {
-					let model  = offset * Matrix4::from_translation(target);
+				State::Seeking { target, ref result, .. } | State::Waiting { target, ref result, .. } | State::Tracking { target, ref result, .. } => {
+					let indicator = match result.status {
+						SolveStatus::Converged                           => ModelId::Indicator,
+						SolveStatus::Incomplete                          => ModelId::IndicatorIncomplete,
+						SolveStatus::Unreachable | SolveStatus::Singular => ModelId::IndicatorFailed,
+					};
+					let model  = offset * Matrix4::from_translation(target.position);
+
+					if let Some(orientation) = target.orientation {
+						let mvp = view_projection * model * Matrix4::from(orientation) * Matrix4::from_scale(2.0);
+						let unlit_uniforms = UnlitUniforms { model_view_projection: UMatrix4(mvp), shadow: None };
+
+						unlit_models.push((render.unlit_models.get(&ModelId::Gnomon).unwrap().clone(), unlit_uniforms));
+					}
+
 					let target = view_projection * model;
 					let shadow = shadow_view_projection * model;
 					let unlit_uniforms = UnlitUniforms { model_view_projection: UMatrix4(target), shadow: Some(UMatrix4(shadow)) };
 
-					unlit_models.push((context.render.unlit_models.get(&ModelId::Indicator).unwrap().clone(), unlit_uniforms.clone()));
+					unlit_models.push((render.unlit_models.get(&indicator).unwrap().clone(), unlit_uniforms.clone()));
 					shadow_casters.push((
-						context.render.unlit_models.get(&ModelId::Indicator).unwrap().clone() as Arc<CastsShadow>,
+						render.unlit_models.get(&indicator).unwrap().clone() as Arc<CastsShadow>,
 						Box::new(unlit_uniforms.clone()) as Box<ShadowUniforms>
 					));
 				},
@@ -230,6 +262,40 @@ impl RenderFrame {
 			};
 		}
 
+		for (index, skeleton) in physics_frame.skeletons.iter().enumerate() {
+			let offset = Matrix4::from_translation(skeleton.position);
+			let (drawn, shadowed) = (visible.contains(&ObjectId::Skeleton(index)), shadowing.contains(&ObjectId::Skeleton(index)));
+
+			for joint in skeleton.visible_joint_transforms() {
+				let joint = offset * joint;
+
+				let uniforms = RenderUniforms {
+					shadow:                UMatrix4(shadow_view_projection * joint),
+					model:                 UMatrix4(joint),
+					model_view_projection: UMatrix4(view_projection * joint),
+				};
+				if drawn {
+					models.push((render.models.get(&ModelId::IKModel).unwrap().clone(), uniforms.clone()));
+				}
+				if shadowed {
+					shadow_casters.push((
+						render.models.get(&ModelId::IKModel).unwrap().clone() as Arc<CastsShadow>,
+						Box::new(uniforms.clone()) as Box<ShadowUniforms>
+					));
+				}
+			}
+
+			for effector in &skeleton.effectors {
+				let model = offset * Matrix4::from_translation(effector.target.position);
+				let unlit_uniforms = UnlitUniforms {
+					model_view_projection: UMatrix4(view_projection * model),
+					shadow:                Some(UMatrix4(shadow_view_projection * model)),
+				};
+
+				unlit_models.push((render.unlit_models.get(&ModelId::Indicator).unwrap().clone(), unlit_uniforms));
+			}
+		}
+
 		RenderFrame {
 			id: physics_frame.frame_counter,
 			models: models,
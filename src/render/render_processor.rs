@@ -1,13 +1,16 @@
+use std::io;
+use std::io::{Write};
 use std::sync::{Arc};
 
 use crossbeam::sync::{MsQueue};
-use glium::{Surface};
+use glium::{DisplayBuild, Surface};
 use glium::backend::glutin_backend::{GlutinFacade};
-use glium::framebuffer::{SimpleFrameBuffer};
-use glium::glutin::{Event, VirtualKeyCode, ElementState};
-use glium::texture::{DepthFormat, DepthTexture2d, MipmapsOption, Texture2d};
+use glium::framebuffer::{DepthRenderBuffer, SimpleFrameBuffer};
+use glium::glutin::{CursorState, Event, ElementState, WindowBuilder, get_primary_monitor};
+use glium::texture::{DepthFormat, DepthTexture2d, MipmapsOption, Texture2d, UncompressedFloatFormat};
 
 use input::{InputEvent};
+use render::image_file::{Image};
 use render::shaders::{UnlitProgram, ForwardProgram, ImageProgram, ShadowProgram};
 use render::render_context::{DEPTH_DIMENSION};
 use render::render_frame::{RenderFrame};
@@ -18,16 +21,33 @@ pub struct RenderProcessor {
 	pub facade: GlutinFacade,
 	pub unlit_program: UnlitProgram,
 	pub image_program: ImageProgram,
+	pub fullscreen: bool,
 
 	q:               Arc<MsQueue<RenderFrame>>,
 	forward_program: ForwardProgram,
 	shadow_program:  ShadowProgram,
 	shadow_texture:  DepthTexture2d,
 	shadow_color:    Texture2d,
+
+	window_size:     (u32, u32), // kept up to date by Resized events
+	windowed_size:   (u32, u32), // what to go back to when leaving fullscreen
+}
+
+pub fn window_builder<'a>(windowed_size: (u32, u32), fullscreen: bool) -> WindowBuilder<'a> {
+	let builder = WindowBuilder::new()
+		.with_title(format!("SG"))
+		.with_depth_buffer(24)
+		.with_decorations(false);
+
+	if fullscreen {
+		builder.with_fullscreen(get_primary_monitor())
+	} else {
+		builder.with_dimensions(windowed_size.0, windowed_size.1)
+	}
 }
 
 impl RenderProcessor {
-	pub fn new(q: Arc<MsQueue<RenderFrame>>, facade: GlutinFacade) -> RenderProcessor {
+	pub fn new(q: Arc<MsQueue<RenderFrame>>, facade: GlutinFacade, window_size: (u32, u32)) -> RenderProcessor {
 		let unlit_program = UnlitProgram::new(&facade);
 		let forward_program = ForwardProgram::new(&facade);
 		let image_program = ImageProgram::new(&facade);
@@ -51,18 +71,36 @@ impl RenderProcessor {
 			image_program: image_program,
 			shadow_texture: shadow_texture,
 			shadow_color: shadow_color,
+			window_size: window_size,
+			windowed_size: window_size,
+			fullscreen: false,
 		}
 	}
 
+	// rebuilds the window in place (GL objects survive), returns the new size
+	// which the caller should pass on as a Resized event, if the platform refuses
+	// the window stays as it was and so does `fullscreen`
+	//
+	pub fn set_fullscreen(&mut self, fullscreen: bool) -> (u32, u32) {
+		if let Err(error) = window_builder(self.windowed_size, fullscreen).rebuild_glium(&self.facade) {
+			let _ = writeln!(io::stderr(), "Could not {} fullscreen: {}", if fullscreen { "switch to" } else { "leave" }, error);
+			return self.window_size;
+		}
+
+		let window = self.facade.get_window().unwrap();
+		window.set_cursor_state(CursorState::Grab).ok();
+
+		self.fullscreen = fullscreen;
+		self.window_size = window.get_inner_size().unwrap_or(self.window_size);
+		self.window_size
+	}
+
 	// returns true to signal caller to exit program and event loop
 	// TODO: should std::process::exit(i32) be used instead?
 	//
-	pub fn handle_system_events(&self) -> Option<Vec<InputEvent>> {
+	pub fn handle_system_events(&mut self) -> Option<Vec<InputEvent>> {
 		let mut out = Vec::new();
 
-		let (width, height) = self.facade.get_window().unwrap().get_inner_size().unwrap();
-		let (width, height) = (width as i32, height as i32);
-
 		for event in self.facade.poll_events() {
 			match event {
 				Event::Closed => {
@@ -70,30 +108,39 @@ impl RenderProcessor {
 					out.push(InputEvent::Quit);
 					return None;
 				},
-				Event::KeyboardInput(_, _, Some(VirtualKeyCode::Escape)) => {
-					println!("Exiting due to escape key");
-					out.push(InputEvent::Quit);
-					return None;
-				},
 				Event::KeyboardInput(state, _, Some(key_code)) => {
 					out.push(InputEvent::KeyboardInput {
 						pressed: state == ElementState::Pressed,
 						id: key_code,
 					});
 				},
+				Event::MouseInput(state, button) => {
+					out.push(InputEvent::MouseInput {
+						pressed: state == ElementState::Pressed,
+						button: button,
+					});
+				},
 				Event::MouseMoved(x, y) => {
-					let (cx, cy) = (width / 2, height / 2);
+					let (cx, cy) = ((self.window_size.0 / 2) as i32, (self.window_size.1 / 2) as i32);
 
 					if x == cx && y == cy { continue }
 
+					// in pixels rather than a fraction of the window, so resizing doesn't change look speed
+					//
 					self.facade.get_window().unwrap().set_cursor_position(cx, cy).ok();
 					out.push(InputEvent::MouseMoved {
-						dx: ((x - cx) as f32) / (cx as f32),
-						dy: ((y - cy) as f32) / (cy as f32),
+						dx: (x - cx) as f32,
+						dy: (y - cy) as f32,
 					});
 				},
-				Event::Resized(_width, _height) => {
-					// TODO: implement and event bus it or something
+				Event::Resized(width, height) => {
+					self.window_size = (width, height);
+					if !self.fullscreen { self.windowed_size = (width, height) }
+
+					out.push(InputEvent::Resized {
+						width: width,
+						height: height,
+					});
 				},
 				_ => ()
 			}
@@ -103,76 +150,99 @@ impl RenderProcessor {
 
 	pub fn handle_render_commands(&mut self) {
 		while let Some(render_frame) = self.q.try_pop() {
-			{
-				let mut frame_buffer = SimpleFrameBuffer::with_depth_buffer(&self.facade, &self.shadow_color, &self.shadow_texture).unwrap();
-
-				frame_buffer.clear_depth(1.0);
-				for (shadow_caster, uniforms) in render_frame.shadow_casters {
-					let uniform_buffer = uniform! {
-						shadow: uniforms.shadow_matrix().clone(),
-					};
-
-					let (vbuffer, index_buffer) = shadow_caster.buffers();
-					match vbuffer {
-						// TODO: can these two cases be unified
-						VertexBufferContainer::Forward{ vertex_buffer } => {
-							frame_buffer.draw(
-								vertex_buffer,
-								index_buffer,
-								&self.shadow_program.program,
-								&uniform_buffer,
-								&self.shadow_program.parameters
-							).unwrap();
-						},
-						VertexBufferContainer::Unlit{ vertex_buffer } => {
-							frame_buffer.draw(
-								vertex_buffer,
-								index_buffer,
-								&self.shadow_program.program,
-								&uniform_buffer,
-								&self.shadow_program.parameters
-							).unwrap();
-						},
-					};
-				}
+			let mut frame = self.facade.draw();
+			self.draw_frame(render_frame, &mut frame);
+			frame.set_finish().unwrap();
+		}
+	}
+
+	// renders into a texture instead of the window and reads it back, for screenshots and golden images
+	// works with a headless facade (glutin falls back to OSMesa, so llvmpipe or softpipe when there is no GPU)
+	//
+	pub fn render_to_image(&self, render_frame: RenderFrame, (width, height): (u32, u32)) -> Image {
+		let color = Texture2d::empty_with_format(
+			&self.facade,
+			UncompressedFloatFormat::U8U8U8U8,
+			MipmapsOption::NoMipmap,
+			width,
+			height
+		).unwrap();
+		let depth = DepthRenderBuffer::new(&self.facade, DepthFormat::I24, width, height).unwrap();
+		{
+			let mut frame_buffer = SimpleFrameBuffer::with_depth_buffer(&self.facade, &color, &depth).unwrap();
+			self.draw_frame(render_frame, &mut frame_buffer);
+		}
+		Image::from_gl_rows(color.read())
+	}
+
+	fn draw_frame<S: Surface>(&self, render_frame: RenderFrame, surface: &mut S) {
+		{
+			let mut frame_buffer = SimpleFrameBuffer::with_depth_buffer(&self.facade, &self.shadow_color, &self.shadow_texture).unwrap();
+
+			frame_buffer.clear_depth(1.0);
+			for (shadow_caster, uniforms) in render_frame.shadow_casters {
+				let uniform_buffer = uniform! {
+					shadow: uniforms.shadow_matrix().clone(),
+				};
+
+				let (vbuffer, index_buffer) = shadow_caster.buffers();
+				match vbuffer {
+					// TODO: can these two cases be unified
+					VertexBufferContainer::Forward{ vertex_buffer } => {
+						frame_buffer.draw(
+							vertex_buffer,
+							index_buffer,
+							&self.shadow_program.program,
+							&uniform_buffer,
+							&self.shadow_program.parameters
+						).unwrap();
+					},
+					VertexBufferContainer::Unlit{ vertex_buffer } => {
+						frame_buffer.draw(
+							vertex_buffer,
+							index_buffer,
+							&self.shadow_program.program,
+							&uniform_buffer,
+							&self.shadow_program.parameters
+						).unwrap();
+					},
+				};
 			}
+		}
 
-			let mut frame = self.facade.draw();
-			frame.clear_color_and_depth((0.125f32, 0.25f32, 0.5f32, 1.0f32), 1.0);
-			{
-				for &(ref model, ref uniforms) in &render_frame.models {
-					let uniform_buffer = uniform! {
-						shadow:                  uniforms.shadow.clone(),
-						shadow_map:              self.shadow_texture.sampled(),
-						model:                   uniforms.model.clone(),
-						model_view_projection:   uniforms.model_view_projection.clone(),
-						reverse_light_direction: render_frame.reverse_light_direction.clone(),
-					};
-					frame.draw(
-						&model.vertex_buffer,
-						&model.index_buffer,
-						&self.forward_program.program,
-						&uniform_buffer,
-						&self.forward_program.parameters
-					).unwrap();
-				}
+		surface.clear_color_and_depth((0.125f32, 0.25f32, 0.5f32, 1.0f32), 1.0);
+		{
+			for &(ref model, ref uniforms) in &render_frame.models {
+				let uniform_buffer = uniform! {
+					shadow:                  uniforms.shadow.clone(),
+					shadow_map:              self.shadow_texture.sampled(),
+					model:                   uniforms.model.clone(),
+					model_view_projection:   uniforms.model_view_projection.clone(),
+					reverse_light_direction: render_frame.reverse_light_direction.clone(),
+				};
+				surface.draw(
+					&model.vertex_buffer,
+					&model.index_buffer,
+					&self.forward_program.program,
+					&uniform_buffer,
+					&self.forward_program.parameters
+				).unwrap();
 			}
-			{
-				for &(ref model, ref uniforms) in &render_frame.unlit_models {
-					let uniform_buffer = uniform! {
-						model_view_projection: uniforms.model_view_projection.clone()
-					};
-
-					frame.draw(
-						&model.vertex_buffer,
-						&model.index_buffer,
-						&self.unlit_program.program,
-						&uniform_buffer,
-						&self.unlit_program.parameters
-					).unwrap();
-				}
+		}
+		{
+			for &(ref model, ref uniforms) in &render_frame.unlit_models {
+				let uniform_buffer = uniform! {
+					model_view_projection: uniforms.model_view_projection.clone()
+				};
+
+				surface.draw(
+					&model.vertex_buffer,
+					&model.index_buffer,
+					&self.unlit_program.program,
+					&uniform_buffer,
+					&self.unlit_program.parameters
+				).unwrap();
 			}
-			frame.set_finish().unwrap();
 		}
 	}
 }
@@ -0,0 +1,30 @@
+use std::f32::consts::{PI};
+
+use cgmath::{Matrix4, Rad, Vector3};
+use rand::{SeedableRng, StdRng};
+use rand::distributions::{IndependentSample, Range};
+
+
+// the static parts of the world, drawn by RenderFrame and collided with through PhysicsContext
+//
+pub const PLAYER_PATH: &'static str = "./data/player.obj";
+pub const LEVEL_PATH:  &'static str = "./data/level.obj";
+pub const TREE_PATH:   &'static str = "./data/tree.obj";
+
+// a row of trees along x = 40, turned at random but the same way every run
+//
+pub fn tree_transforms() -> Vec<Matrix4<f32>> {
+	const D: f32 = 8f32;
+	const A: f32 = 40f32;
+
+	let seed: &[_] = &[2, 2, 2, 2];
+	let mut rng: StdRng = SeedableRng::from_seed(seed);
+	let range = Range::new(0f32, PI * 0.5);
+
+	let zs = [D * -3.0, D * 3.0, D * -2.0, D * 2.0, D, -D, 0.0, A, -A];
+
+	zs.iter().map(|&z| {
+		Matrix4::from_translation(Vector3::new(A, 0.0, z)) *
+		Matrix4::from_angle_y(Rad(range.ind_sample(&mut rng)))
+	}).collect()
+}